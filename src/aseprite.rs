@@ -0,0 +1,739 @@
+use std::{io::Read, time::Duration};
+
+use bevy::{
+  asset::{AssetLoader, LoadContext, LoadedAsset},
+  prelude::*,
+  reflect::TypeUuid,
+  render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+  utils::BoxedFuture,
+};
+use flate2::read::ZlibDecoder;
+
+pub mod prelude {
+  pub use super::AsepritePlugin;
+}
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+const HEADER_SIZE: usize = 128;
+
+const OLD_PALETTE_CHUNK: u16 = 0x0004;
+const LAYER_CHUNK: u16 = 0x2004;
+const CEL_CHUNK: u16 = 0x2005;
+const TAGS_CHUNK: u16 = 0x2018;
+const PALETTE_CHUNK: u16 = 0x2019;
+const SLICE_CHUNK: u16 = 0x2022;
+
+const LAYER_VISIBLE: u16 = 1;
+const LAYER_BACKGROUND: u16 = 8;
+const HEADER_LAYER_OPACITY_VALID: u32 = 1;
+const SLICE_NINE_PATCH: u32 = 1;
+const SLICE_PIVOT: u32 = 2;
+// Indexed images can't refer to more colors than a byte can hold.
+const MAX_PALETTE_SIZE: usize = 256;
+
+#[derive(Debug)]
+pub enum AsepriteError {
+  NotAseprite,
+  UnexpectedEnd,
+  UnsupportedColorDepth(u16),
+  Compression(std::io::Error),
+  NoFrames,
+  // A cel whose image has no width or height.
+  EmptyCel,
+  // A palette chunk with more entries than an indexed image can use, or a range outside of it.
+  InvalidPalette,
+  // Frames too large to lay out side by side in a single image.
+  TooLarge,
+}
+
+impl std::fmt::Display for AsepriteError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AsepriteError::NotAseprite => write!(f, "not an aseprite file"),
+      AsepriteError::UnexpectedEnd => write!(f, "the file ends in the middle of a chunk"),
+      AsepriteError::UnsupportedColorDepth(depth) => write!(f, "unsupported color depth of {} bits", depth),
+      AsepriteError::Compression(error) => write!(f, "invalid compressed cel: {}", error),
+      AsepriteError::NoFrames => write!(f, "the file has no frames"),
+      AsepriteError::EmptyCel => write!(f, "a cel has an empty image"),
+      AsepriteError::InvalidPalette => write!(f, "the palette doesn't fit in {} entries", MAX_PALETTE_SIZE),
+      AsepriteError::TooLarge => write!(f, "the frames are too large to fit in one image"),
+    }
+  }
+}
+
+impl std::error::Error for AsepriteError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagDirection {
+  Forward,
+  Reverse,
+  PingPong,
+  PingPongReverse,
+}
+
+// A named range of frames, which is how aseprite files mark animations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsepriteTag {
+  pub name: String,
+  pub from: usize,
+  pub to: usize,
+  pub direction: TagDirection,
+}
+
+impl AsepriteTag {
+  pub fn frames(&self) -> std::ops::RangeInclusive<usize> {
+    self.from..=self.to
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SliceRect {
+  pub position: IVec2,
+  pub size: UVec2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SliceKey {
+  // First frame from which on the slice has these bounds.
+  pub frame: usize,
+  pub bounds: SliceRect,
+  pub center: Option<SliceRect>,
+  pub pivot: Option<IVec2>,
+}
+
+// A named region of the sprite, for example a hitbox or a 9-patch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsepriteSlice {
+  pub name: String,
+  pub keys: Vec<SliceKey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsepriteLayer {
+  pub name: String,
+  pub visible: bool,
+  pub opacity: u8,
+  // Nesting depth inside layer groups.
+  pub child_level: u16,
+  pub is_group: bool,
+  is_background: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Cel {
+  layer: usize,
+  position: IVec2,
+  opacity: u8,
+  size: UVec2,
+  pixels: Vec<[u8; 4]>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Frame {
+  duration: Duration,
+  cels: Vec<Cel>,
+}
+
+// A parsed aseprite file. The visible layers of every frame are flattened into an image
+// which has the frames side by side, and a matching texture atlas with one cell per
+// frame, available as the `image` and `atlas` labels of the asset.
+#[derive(Debug, TypeUuid)]
+#[uuid = "5b1f2c3e-8a4d-4f6b-9c0e-7d2a1b3c4e5f"]
+pub struct Aseprite {
+  pub frame_size: UVec2,
+  pub frame_durations: Vec<Duration>,
+  pub layers: Vec<AsepriteLayer>,
+  pub tags: Vec<AsepriteTag>,
+  pub slices: Vec<AsepriteSlice>,
+  pub image: Handle<Image>,
+  pub atlas: Handle<TextureAtlas>,
+}
+
+impl Aseprite {
+  pub fn frame_count(&self) -> usize {
+    self.frame_durations.len()
+  }
+
+  pub fn tag(&self, name: &str) -> Option<&AsepriteTag> {
+    self.tags.iter().find(|tag| tag.name == name)
+  }
+
+  pub fn slice(&self, name: &str) -> Option<&AsepriteSlice> {
+    self.slices.iter().find(|slice| slice.name == name)
+  }
+
+  // Size of the image with all frames side by side.
+  pub fn image_size(&self) -> UVec2 {
+    UVec2::new(self.frame_size.x * self.frame_count() as u32, self.frame_size.y)
+  }
+
+  // Part of the image a frame takes up, in UV coordinates.
+  pub fn frame_uv(&self, frame: usize) -> Rect {
+    let frames = self.frame_count().max(1) as f32;
+    Rect::new(frame as f32 / frames, 0.0, (frame + 1) as f32 / frames, 1.0)
+  }
+}
+
+struct Reader<'a> {
+  bytes: &'a [u8],
+  position: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn new(bytes: &'a [u8]) -> Reader<'a> {
+    Reader { bytes, position: 0 }
+  }
+
+  fn take(&mut self, count: usize) -> Result<&'a [u8], AsepriteError> {
+    let end = self.position.checked_add(count).filter(|end| *end <= self.bytes.len()).ok_or(AsepriteError::UnexpectedEnd)?;
+    let bytes = &self.bytes[self.position..end];
+    self.position = end;
+    Ok(bytes)
+  }
+
+  fn skip(&mut self, count: usize) -> Result<(), AsepriteError> {
+    self.take(count).map(|_| ())
+  }
+
+  fn byte(&mut self) -> Result<u8, AsepriteError> {
+    Ok(self.take(1)?[0])
+  }
+
+  fn word(&mut self) -> Result<u16, AsepriteError> {
+    Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+  }
+
+  fn short(&mut self) -> Result<i16, AsepriteError> {
+    Ok(i16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+  }
+
+  fn dword(&mut self) -> Result<u32, AsepriteError> {
+    Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+  }
+
+  fn long(&mut self) -> Result<i32, AsepriteError> {
+    Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+  }
+
+  fn string(&mut self) -> Result<String, AsepriteError> {
+    let length = self.word()? as usize;
+    Ok(String::from_utf8_lossy(self.take(length)?).into_owned())
+  }
+
+  fn rect(&mut self) -> Result<SliceRect, AsepriteError> {
+    let position = IVec2::new(self.long()?, self.long()?);
+    let size = UVec2::new(self.dword()?, self.dword()?);
+    Ok(SliceRect { position, size })
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ColorDepth {
+  Rgba,
+  Grayscale,
+  Indexed { transparent_index: u8 },
+}
+
+impl ColorDepth {
+  fn bytes_per_pixel(&self) -> usize {
+    match self {
+      ColorDepth::Rgba => 4,
+      ColorDepth::Grayscale => 2,
+      ColorDepth::Indexed { .. } => 1,
+    }
+  }
+
+  fn pixels(&self, bytes: &[u8], palette: &[[u8; 4]], opaque: bool) -> Vec<[u8; 4]> {
+    bytes
+      .chunks_exact(self.bytes_per_pixel())
+      .map(|pixel| match self {
+        ColorDepth::Rgba => [pixel[0], pixel[1], pixel[2], pixel[3]],
+        ColorDepth::Grayscale => [pixel[0], pixel[0], pixel[0], pixel[1]],
+        ColorDepth::Indexed { transparent_index } if pixel[0] == *transparent_index && !opaque => [0; 4],
+        ColorDepth::Indexed { .. } => palette.get(pixel[0] as usize).copied().unwrap_or([0; 4]),
+      })
+      .collect()
+  }
+}
+
+// The parts of a file needed to turn it into images and metadata.
+struct ParsedAseprite {
+  frame_size: UVec2,
+  // The size of all frames side by side, checked to fit in memory as RGBA.
+  image_size: UVec2,
+  image_bytes: usize,
+  layers: Vec<AsepriteLayer>,
+  frames: Vec<Frame>,
+  tags: Vec<AsepriteTag>,
+  slices: Vec<AsepriteSlice>,
+  layer_opacity_valid: bool,
+}
+
+fn parse(bytes: &[u8]) -> Result<ParsedAseprite, AsepriteError> {
+  let mut header = Reader::new(bytes);
+  header.dword()?;
+  if header.word()? != HEADER_MAGIC {
+    return Err(AsepriteError::NotAseprite);
+  }
+  let frame_count = header.word()? as usize;
+  if frame_count == 0 {
+    return Err(AsepriteError::NoFrames);
+  }
+  let frame_size = UVec2::new(header.word()? as u32, header.word()? as u32);
+  let image_size = UVec2::new(
+    frame_size.x.checked_mul(frame_count as u32).ok_or(AsepriteError::TooLarge)?,
+    frame_size.y,
+  );
+  let image_bytes = image_size
+    .x
+    .checked_mul(image_size.y)
+    .and_then(|pixels| pixels.checked_mul(4))
+    .ok_or(AsepriteError::TooLarge)? as usize;
+  let depth = header.word()?;
+  let flags = header.dword()?;
+  header.skip(2 + 4 + 4)?;
+  let transparent_index = header.byte()?;
+  let color_depth = match depth {
+    32 => ColorDepth::Rgba,
+    16 => ColorDepth::Grayscale,
+    8 => ColorDepth::Indexed { transparent_index },
+    depth => return Err(AsepriteError::UnsupportedColorDepth(depth)),
+  };
+
+  let mut parsed = ParsedAseprite {
+    frame_size,
+    image_size,
+    image_bytes,
+    layers: Vec::new(),
+    frames: Vec::new(),
+    tags: Vec::new(),
+    slices: Vec::new(),
+    layer_opacity_valid: flags & HEADER_LAYER_OPACITY_VALID != 0,
+  };
+  let mut palette = vec![[0u8; 4]; MAX_PALETTE_SIZE];
+  let mut has_new_palette = false;
+
+  let mut reader = Reader::new(bytes);
+  reader.skip(HEADER_SIZE)?;
+  for frame_index in 0..frame_count {
+    let frame_start = reader.position;
+    let frame_length = reader.dword()? as usize;
+    if reader.word()? != FRAME_MAGIC {
+      return Err(AsepriteError::NotAseprite);
+    }
+    let old_chunk_count = reader.word()? as usize;
+    let duration = Duration::from_millis(reader.word()? as u64);
+    reader.skip(2)?;
+    let chunk_count = match reader.dword()? as usize {
+      0 => old_chunk_count,
+      count => count,
+    };
+
+    let mut frame = Frame { duration, cels: Vec::new() };
+    for _ in 0..chunk_count {
+      let chunk_start = reader.position;
+      let chunk_length = reader.dword()? as usize;
+      let chunk_type = reader.word()?;
+      let mut chunk = Reader::new(reader.take(chunk_length.saturating_sub(6))?);
+
+      match chunk_type {
+        LAYER_CHUNK => {
+          let flags = chunk.word()?;
+          let layer_type = chunk.word()?;
+          let child_level = chunk.word()?;
+          chunk.skip(2 + 2 + 2)?;
+          let opacity = chunk.byte()?;
+          chunk.skip(3)?;
+          parsed.layers.push(AsepriteLayer {
+            name: chunk.string()?,
+            visible: flags & LAYER_VISIBLE != 0,
+            opacity,
+            child_level,
+            is_group: layer_type == 1,
+            is_background: flags & LAYER_BACKGROUND != 0,
+          });
+        }
+        CEL_CHUNK => {
+          let layer = chunk.word()? as usize;
+          let position = IVec2::new(chunk.short()? as i32, chunk.short()? as i32);
+          let opacity = chunk.byte()?;
+          let cel_type = chunk.word()?;
+          chunk.skip(7)?;
+          let opaque = parsed.layers.get(layer).is_some_and(|layer| layer.is_background);
+          match cel_type {
+            // raw and compressed images
+            0 | 2 => {
+              let size = UVec2::new(chunk.word()? as u32, chunk.word()? as u32);
+              if size.x == 0 || size.y == 0 {
+                return Err(AsepriteError::EmptyCel);
+              }
+              let data = chunk.take(chunk.bytes.len() - chunk.position)?;
+              let pixel_bytes = match cel_type {
+                0 => data.to_vec(),
+                _ => {
+                  let mut decompressed = Vec::new();
+                  ZlibDecoder::new(data).read_to_end(&mut decompressed).map_err(AsepriteError::Compression)?;
+                  decompressed
+                }
+              };
+              let pixels = color_depth.pixels(&pixel_bytes, &palette, opaque);
+              frame.cels.push(Cel { layer, position, opacity, size, pixels });
+            }
+            // linked cels reuse the cel of the same layer in an earlier frame
+            1 => {
+              let linked_frame = chunk.word()? as usize;
+              let linked = parsed
+                .frames
+                .get(linked_frame)
+                .and_then(|frame: &Frame| frame.cels.iter().find(|cel| cel.layer == layer))
+                .cloned();
+              if let Some(cel) = linked {
+                frame.cels.push(Cel { position, opacity, ..cel });
+              }
+            }
+            // tilemap cels aren't used by any of the game's files
+            _ => {}
+          }
+        }
+        PALETTE_CHUNK => {
+          has_new_palette = true;
+          let size = chunk.dword()? as usize;
+          let first = chunk.dword()? as usize;
+          let last = chunk.dword()? as usize;
+          chunk.skip(8)?;
+          if size > MAX_PALETTE_SIZE || first > last || last >= MAX_PALETTE_SIZE {
+            return Err(AsepriteError::InvalidPalette);
+          }
+          for index in first..=last {
+            let entry_flags = chunk.word()?;
+            let color = [chunk.byte()?, chunk.byte()?, chunk.byte()?, chunk.byte()?];
+            if entry_flags & 1 != 0 {
+              chunk.string()?;
+            }
+            if let Some(entry) = palette.get_mut(index) {
+              *entry = color;
+            }
+          }
+        }
+        OLD_PALETTE_CHUNK if !has_new_palette => {
+          let packets = chunk.word()?;
+          let mut index = 0;
+          for _ in 0..packets {
+            index += chunk.byte()? as usize;
+            let count = match chunk.byte()? {
+              0 => 256,
+              count => count as usize,
+            };
+            for _ in 0..count {
+              let color = [chunk.byte()?, chunk.byte()?, chunk.byte()?, 255];
+              if let Some(entry) = palette.get_mut(index) {
+                *entry = color;
+              }
+              index += 1;
+            }
+          }
+        }
+        TAGS_CHUNK => {
+          let count = chunk.word()?;
+          chunk.skip(8)?;
+          for _ in 0..count {
+            let from = chunk.word()? as usize;
+            let to = chunk.word()? as usize;
+            let direction = match chunk.byte()? {
+              1 => TagDirection::Reverse,
+              2 => TagDirection::PingPong,
+              3 => TagDirection::PingPongReverse,
+              _ => TagDirection::Forward,
+            };
+            chunk.skip(2 + 6 + 3 + 1)?;
+            parsed.tags.push(AsepriteTag { name: chunk.string()?, from, to, direction });
+          }
+        }
+        SLICE_CHUNK => {
+          let key_count = chunk.dword()?;
+          let flags = chunk.dword()?;
+          chunk.skip(4)?;
+          let name = chunk.string()?;
+          let mut keys = Vec::new();
+          for _ in 0..key_count {
+            let frame = chunk.dword()? as usize;
+            let bounds = chunk.rect()?;
+            let center = match flags & SLICE_NINE_PATCH != 0 {
+              true => Some(chunk.rect()?),
+              false => None,
+            };
+            let pivot = match flags & SLICE_PIVOT != 0 {
+              true => Some(IVec2::new(chunk.long()?, chunk.long()?)),
+              false => None,
+            };
+            keys.push(SliceKey { frame, bounds, center, pivot });
+          }
+          parsed.slices.push(AsepriteSlice { name, keys });
+        }
+        _ => {}
+      }
+      reader.position = chunk_start + chunk_length.max(6);
+    }
+
+    parsed.frames.push(frame);
+    reader.position = frame_start + frame_length;
+    if frame_index + 1 < frame_count && reader.position > bytes.len() {
+      return Err(AsepriteError::UnexpectedEnd);
+    }
+  }
+
+  Ok(parsed)
+}
+
+// Layers hidden themselves or inside a hidden group don't show up in the image.
+fn visible_layers(layers: &[AsepriteLayer]) -> Vec<bool> {
+  let mut hidden_below: Option<u16> = None;
+  layers
+    .iter()
+    .map(|layer| {
+      if hidden_below.is_some_and(|level| layer.child_level <= level) {
+        hidden_below = None;
+      }
+      if hidden_below.is_none() && !layer.visible {
+        hidden_below = Some(layer.child_level);
+      }
+      hidden_below.is_none() && !layer.is_group
+    })
+    .collect()
+}
+
+// Draws `color` over `target`, both with straight alpha.
+fn blend(target: &mut [u8], color: [u8; 4], opacity: f32) {
+  let source_alpha = color[3] as f32 / 255.0 * opacity;
+  if source_alpha <= 0.0 {
+    return;
+  }
+  let target_alpha = target[3] as f32 / 255.0;
+  let alpha = source_alpha + target_alpha * (1.0 - source_alpha);
+  for channel in 0..3 {
+    let source = color[channel] as f32;
+    let existing = target[channel] as f32;
+    target[channel] = ((source * source_alpha + existing * target_alpha * (1.0 - source_alpha)) / alpha).round() as u8;
+  }
+  target[3] = (alpha * 255.0).round() as u8;
+}
+
+// Flattens the visible layers of every frame into an RGBA image with the frames side by side.
+fn render_frames(parsed: &ParsedAseprite) -> Vec<u8> {
+  let width = parsed.image_size.x as usize;
+  let mut data = vec![0u8; parsed.image_bytes];
+  let visible = visible_layers(&parsed.layers);
+
+  for (frame_index, frame) in parsed.frames.iter().enumerate() {
+    let mut cels: Vec<_> = frame.cels.iter().filter(|cel| visible.get(cel.layer).copied().unwrap_or(false)).collect();
+    cels.sort_by_key(|cel| cel.layer);
+
+    for cel in cels {
+      let layer_opacity = match parsed.layer_opacity_valid {
+        true => parsed.layers[cel.layer].opacity as f32 / 255.0,
+        false => 1.0,
+      };
+      let opacity = cel.opacity as f32 / 255.0 * layer_opacity;
+      for (index, color) in cel.pixels.iter().enumerate() {
+        let pixel = cel.position + IVec2::new((index as u32 % cel.size.x) as i32, (index as u32 / cel.size.x) as i32);
+        if pixel.x < 0 || pixel.y < 0 || pixel.x >= parsed.frame_size.x as i32 || pixel.y >= parsed.frame_size.y as i32 {
+          continue;
+        }
+        let x = frame_index * parsed.frame_size.x as usize + pixel.x as usize;
+        let offset = (pixel.y as usize * width + x) * 4;
+        blend(&mut data[offset..offset + 4], *color, opacity);
+      }
+    }
+  }
+  data
+}
+
+#[derive(Default)]
+pub struct AsepriteLoader;
+
+impl AssetLoader for AsepriteLoader {
+  fn load<'a>(
+    &'a self,
+    bytes: &'a [u8],
+    load_context: &'a mut LoadContext,
+  ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+    Box::pin(async move {
+      let parsed = parse(bytes)?;
+      let image = Image::new(
+        Extent3d { width: parsed.image_size.x, height: parsed.image_size.y, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        render_frames(&parsed),
+        TextureFormat::Rgba8UnormSrgb,
+      );
+      let image = load_context.set_labeled_asset("image", LoadedAsset::new(image));
+      let atlas = TextureAtlas::from_grid(
+        image.clone(),
+        parsed.frame_size.as_vec2(),
+        parsed.frames.len(),
+        1,
+        None,
+        None,
+      );
+      let atlas = load_context.set_labeled_asset("atlas", LoadedAsset::new(atlas));
+
+      load_context.set_default_asset(LoadedAsset::new(Aseprite {
+        frame_size: parsed.frame_size,
+        frame_durations: parsed.frames.iter().map(|frame| frame.duration).collect(),
+        layers: parsed.layers,
+        tags: parsed.tags,
+        slices: parsed.slices,
+        image,
+        atlas,
+      }));
+      Ok(())
+    })
+  }
+
+  fn extensions(&self) -> &[&str] {
+    &["aseprite", "ase"]
+  }
+}
+
+// Loads `.aseprite` files. Use the `#image` and `#atlas` labels to get the flattened frames.
+pub struct AsepritePlugin;
+
+impl Plugin for AsepritePlugin {
+  fn build(&self, app: &mut App) {
+    app.add_asset::<Aseprite>().init_asset_loader::<AsepriteLoader>();
+  }
+}
+
+#[cfg(test)]
+mod aseprite_test {
+  use bevy::render::texture::{CompressedImageFormats, ImageType};
+
+  use super::*;
+
+  // The game's aseprite files should render exactly like the PNGs exported from them.
+  fn assert_matches_export(aseprite: &[u8], png: &[u8], frames: usize) {
+    let parsed = parse(aseprite).unwrap();
+    assert_eq!(parsed.frames.len(), frames);
+    let rendered = render_frames(&parsed);
+
+    let exported = Image::from_buffer(png, ImageType::Extension("png"), CompressedImageFormats::NONE, true).unwrap();
+    assert_eq!(exported.size(), Vec2::new((parsed.frame_size.x as usize * frames) as f32, parsed.frame_size.y as f32));
+    for (rendered, exported) in rendered.chunks_exact(4).zip(exported.data.chunks_exact(4)) {
+      assert_eq!(rendered[3], exported[3]);
+      if exported[3] != 0 {
+        assert_eq!(rendered, exported);
+      }
+    }
+  }
+
+  #[test]
+  fn conveyor_matches_export() {
+    assert_matches_export(
+      include_bytes!("../assets/conveyor.aseprite"),
+      include_bytes!("../assets/conveyor.png"),
+      116,
+    );
+  }
+
+  #[test]
+  fn layers_are_flattened() {
+    assert_matches_export(
+      include_bytes!("../assets/background.aseprite"),
+      include_bytes!("../assets/background.png"),
+      14,
+    );
+  }
+
+  // A 32 bit file with `frames` frames of 2x2 pixels.
+  fn header(frames: u16) -> Vec<u8> {
+    let mut header = vec![0u8; HEADER_SIZE];
+    header[4..6].copy_from_slice(&HEADER_MAGIC.to_le_bytes());
+    header[6..8].copy_from_slice(&frames.to_le_bytes());
+    header[8..10].copy_from_slice(&2u16.to_le_bytes());
+    header[10..12].copy_from_slice(&2u16.to_le_bytes());
+    header[12..14].copy_from_slice(&32u16.to_le_bytes());
+    header
+  }
+
+  // A frame holding a single raw cel of the given size.
+  fn frame_with_cel(size: UVec2) -> Vec<u8> {
+    let mut cel = Vec::new();
+    cel.extend_from_slice(&[0; 2 + 2 + 2]);
+    cel.push(255);
+    cel.extend_from_slice(&0u16.to_le_bytes());
+    cel.extend_from_slice(&[0; 7]);
+    cel.extend_from_slice(&(size.x as u16).to_le_bytes());
+    cel.extend_from_slice(&(size.y as u16).to_le_bytes());
+    let pixel_bytes = size.x.checked_mul(size.y).and_then(|pixels| pixels.checked_mul(4)).expect("cel too large");
+    cel.resize(cel.len() + pixel_bytes as usize, 255);
+
+    frame_with_chunk(CEL_CHUNK, cel)
+  }
+
+  // A frame holding a single chunk of the given type.
+  fn frame_with_chunk(chunk_type: u16, body: Vec<u8>) -> Vec<u8> {
+    let mut chunk = ((body.len() + 6) as u32).to_le_bytes().to_vec();
+    chunk.extend_from_slice(&chunk_type.to_le_bytes());
+    chunk.extend(body);
+
+    let mut frame = ((chunk.len() + 16) as u32).to_le_bytes().to_vec();
+    frame.extend_from_slice(&FRAME_MAGIC.to_le_bytes());
+    frame.extend_from_slice(&1u16.to_le_bytes());
+    frame.extend_from_slice(&100u16.to_le_bytes());
+    frame.extend_from_slice(&[0; 2]);
+    frame.extend_from_slice(&1u32.to_le_bytes());
+    frame.extend(chunk);
+    frame
+  }
+
+  #[test]
+  fn rejects_other_files() {
+    assert!(matches!(parse(include_bytes!("../assets/conveyor.png")), Err(AsepriteError::NotAseprite)));
+  }
+
+  #[test]
+  fn rejects_empty_files_and_cels() {
+    assert!(matches!(parse(&header(0)), Err(AsepriteError::NoFrames)));
+
+    let file = [header(1), frame_with_cel(UVec2::new(2, 2))].concat();
+    assert_eq!(parse(&file).unwrap().frames[0].cels.len(), 1);
+    let file = [header(1), frame_with_cel(UVec2::new(0, 2))].concat();
+    assert!(matches!(parse(&file), Err(AsepriteError::EmptyCel)));
+    let file = [header(1), frame_with_cel(UVec2::new(2, 0))].concat();
+    assert!(matches!(parse(&file), Err(AsepriteError::EmptyCel)));
+  }
+
+  // A palette chunk declaring `size` entries and setting `first..=last` to white.
+  fn palette(size: u32, first: u32, last: u32) -> Vec<u8> {
+    let mut body = Vec::new();
+    for value in [size, first, last] {
+      body.extend_from_slice(&value.to_le_bytes());
+    }
+    body.extend_from_slice(&[0; 8]);
+    for _ in first..=last.min(first + 1) {
+      body.extend_from_slice(&0u16.to_le_bytes());
+      body.extend_from_slice(&[255; 4]);
+    }
+    frame_with_chunk(PALETTE_CHUNK, body)
+  }
+
+  #[test]
+  fn rejects_oversized_palettes() {
+    let file = [header(1), palette(2, 0, 1)].concat();
+    assert!(parse(&file).is_ok());
+    let file = [header(1), palette(u32::MAX, 0, 1)].concat();
+    assert!(matches!(parse(&file), Err(AsepriteError::InvalidPalette)));
+    let file = [header(1), palette(2, 1, 0)].concat();
+    assert!(matches!(parse(&file), Err(AsepriteError::InvalidPalette)));
+    let file = [header(1), palette(256, 256, 257)].concat();
+    assert!(matches!(parse(&file), Err(AsepriteError::InvalidPalette)));
+  }
+
+  #[test]
+  fn rejects_images_too_large_to_allocate() {
+    let mut file = [header(u16::MAX), frame_with_cel(UVec2::new(2, 2))].concat();
+    file[8..10].copy_from_slice(&u16::MAX.to_le_bytes());
+    file[10..12].copy_from_slice(&u16::MAX.to_le_bytes());
+    assert!(matches!(parse(&file), Err(AsepriteError::TooLarge)));
+  }
+}
@@ -3,6 +3,8 @@ pub mod mouse_input;
 use bevy::{input::Input, math::Vec3, prelude::*, render::camera::Camera};
 use bevy_pixel_camera::PixelProjection;
 
+use crate::settings::prelude::*;
+
 pub mod prelude {
   pub use super::mouse_input::CursorPos;
   pub use super::mouse_input::update_cursor_pos;
@@ -17,6 +19,7 @@ pub struct CameraMoved();
 pub fn movement(
   time: Res<Time>,
   keyboard_input: Res<Input<KeyCode>>,
+  settings: Res<Settings>,
   mut camera_move_event: EventWriter<CameraMoved>,
   mut query: Query<(&mut Transform, &mut PixelProjection), With<Camera>>,
 ) {
@@ -59,7 +62,7 @@ pub fn movement(
     }
 
     let z = transform.translation.z;
-    transform.translation += time.delta_seconds() * direction * 500.;
+    transform.translation += time.delta_seconds() * direction * settings.pan_speed;
     if round_transform {
       transform.translation.round();
     }
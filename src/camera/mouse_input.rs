@@ -13,6 +13,15 @@ impl Default for CursorPos {
 }
 
 impl CursorPos {
+  pub fn world(&self) -> Vec2 {
+    self.0
+  }
+
+  // Position in the window, in logical pixels from the bottom left.
+  pub fn screen(&self) -> Vec2 {
+    self.1
+  }
+
   pub fn to_map_pos(&self, map_transform: &Transform) -> Vec2 {
     // Grab the cursor position from the `Res<CursorPos>`
     let cursor_pos: Vec2 = self.0;
@@ -0,0 +1,107 @@
+use std::{path::PathBuf, str::FromStr};
+
+use bevy::prelude::UVec2;
+use clap::{Parser, Subcommand};
+
+use crate::tile::prelude::PlayfieldSize;
+
+pub const DEFAULT_PLAYFIELD_SIZE: Size = Size(UVec2::new(32, 32));
+pub const DEFAULT_ZOOM: i32 = 4;
+
+// A `<width>x<height>` pair such as `32x32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Size(pub UVec2);
+
+impl FromStr for Size {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let error = || format!("expected <width>x<height>, got `{}`", value);
+    let (width, height) = value.split_once('x').ok_or_else(error)?;
+    let width: u32 = width.trim().parse().map_err(|_| error())?;
+    let height: u32 = height.trim().parse().map_err(|_| error())?;
+    if width == 0 || height == 0 {
+      return Err(format!("`{}` has no area", value));
+    }
+    Ok(Size(UVec2::new(width, height)))
+  }
+}
+
+// A `Size` which also fits the bounds of a playfield.
+fn parse_playfield(value: &str) -> Result<Size, String> {
+  let size: Size = value.parse()?;
+  PlayfieldSize::new(size.0)?;
+  Ok(size)
+}
+
+#[derive(Debug, Parser)]
+#[command(about = "A conveyor building game made for Ludum Dare 53")]
+pub struct Cli {
+  #[command(subcommand)]
+  pub command: Option<Command>,
+
+  /// Size of the playfield in tiles, e.g. 32x32. Defaults to the size stored in the loaded save file
+  #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_playfield)]
+  pub playfield: Option<Size>,
+
+  /// Save file to load at startup
+  #[arg(long, value_name = "FILE")]
+  pub load: Option<PathBuf>,
+
+  /// Window size in pixels, e.g. 1280x720. Defaults to the size in the settings
+  #[arg(long, value_name = "WIDTHxHEIGHT")]
+  pub window_size: Option<Size>,
+
+  /// Start in borderless fullscreen, whatever the settings say
+  #[arg(long)]
+  pub fullscreen: bool,
+
+  /// Starting camera zoom. Defaults to the zoom in the settings
+  #[arg(long, value_parser = clap::value_parser!(i32).range(1..))]
+  pub zoom: Option<i32>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+  /// Simulate a save file without a window and print a JSON report
+  Headless {
+    /// Save file to simulate
+    layout: PathBuf,
+
+    /// Number of simulation ticks to run
+    #[arg(long, default_value_t = crate::headless::DEFAULT_HEADLESS_TICKS)]
+    ticks: u64,
+  },
+  /// Check a save file for layout problems, exiting with a non-zero status if any are found
+  Validate {
+    /// Save file to check
+    layout: PathBuf,
+  },
+}
+
+#[cfg(test)]
+mod cli_test {
+  use clap::CommandFactory;
+
+  use super::*;
+
+  #[test]
+  fn parse_size() {
+    assert_eq!("16x9".parse::<Size>(), Ok(Size(UVec2::new(16, 9))));
+    assert!("16".parse::<Size>().is_err());
+    assert!("0x9".parse::<Size>().is_err());
+    assert!("ax9".parse::<Size>().is_err());
+  }
+
+  #[test]
+  fn parse_playfield_size() {
+    assert_eq!(parse_playfield("16x9"), Ok(Size(UVec2::new(16, 9))));
+    assert!(parse_playfield("0x9").is_err());
+    assert!(parse_playfield("4000000000x9").is_err());
+  }
+
+  #[test]
+  fn cli_is_consistent() {
+    Cli::command().debug_assert();
+  }
+}
@@ -0,0 +1,64 @@
+use bevy::{input::common_conditions::input_toggle_active, prelude::*};
+use bevy_inspector_egui::quick::{ResourceInspectorPlugin, WorldInspectorPlugin};
+
+use crate::{
+  delivery::prelude::*,
+  economy::{prelude::*, Purchased},
+  settings::prelude::*,
+  input::{chained_tile::ChainedTileResource, egui_check::EguiCapturedResources, prelude::*},
+  localisation::prelude::*,
+  simulation::prelude::*,
+  stats::PackageKind,
+  tile::{overlay::ConveyorOverlay, placement::PreviousPlaceAttempt, prelude::*},
+  ui::{BuildMenuState, DebugOverlayState, SettingsMenuState, StatsWindowState, TileInspectorState},
+};
+
+pub mod prelude {
+  pub use super::DebugPlugin;
+}
+
+const INSPECTOR_TOGGLE_KEY: KeyCode = KeyCode::F1;
+
+// Registers the game's reflected types and shows inspectors for the world and the
+// simulation clock, toggled with F1. Only built with the `debug` feature.
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      // input
+      .register_type::<ChainedTileResource>()
+      .register_type::<EguiCapturedResources>()
+      .register_type::<SelectedTileDirection>()
+      .register_type::<SelectedTileType>()
+      // tiles
+      .register_type::<ConveyorDirection>()
+      .register_type::<TileType>()
+      .register_type::<TileCategory>()
+      .register_type::<PreviousPlaceAttempt>()
+      .register_type::<ConveyorOverlay>()
+      // simulation
+      .register_type::<SimulationSpeed>()
+      .register_type::<SimulationTime>()
+      .register_type::<PackageKind>()
+      .register_type::<Customer>()
+      .register_type::<DeliveryScore>()
+      .register_type::<Wallet>()
+      .register_type::<Purchased>()
+      // settings
+      .register_type::<Settings>()
+      .register_type::<WindowModeSetting>()
+      .register_type::<ColorPalette>()
+      .register_type::<Language>()
+      // ui
+      .register_type::<BuildMenuState>()
+      .register_type::<DebugOverlayState>()
+      .register_type::<SettingsMenuState>()
+      .register_type::<StatsWindowState>()
+      .register_type::<TileInspectorState>()
+      .add_plugin(WorldInspectorPlugin::new().run_if(input_toggle_active(false, INSPECTOR_TOGGLE_KEY)))
+      .add_plugin(
+        ResourceInspectorPlugin::<SimulationTime>::new().run_if(input_toggle_active(false, INSPECTOR_TOGGLE_KEY)),
+      );
+  }
+}
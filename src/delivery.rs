@@ -0,0 +1,66 @@
+pub mod customer;
+pub mod orders;
+pub mod packages;
+
+use bevy::prelude::*;
+
+use crate::{simulation::{prelude::*, run_simulation_schedule}, tile::TileSetupSystemSet, GameSystemSet};
+
+use self::{customer::plugin_exports::*, orders::plugin_exports::*, packages::plugin_exports::*};
+
+pub mod prelude {
+  pub use super::customer::prelude::*;
+  pub use super::orders::prelude::*;
+  pub use super::DeliveryPlugin;
+}
+
+pub struct DeliveryPlugin {
+  include_textures: bool,
+}
+
+impl DeliveryPlugin {
+  pub fn new() -> DeliveryPlugin {
+    DeliveryPlugin { include_textures: true }
+  }
+
+  pub fn new_headless() -> DeliveryPlugin {
+    DeliveryPlugin { include_textures: false }
+  }
+}
+
+impl Plugin for DeliveryPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<Orders>()
+      .init_resource::<DeliveryScore>()
+      .add_event::<PackageDelivered>()
+      .add_event::<OrderEvent>()
+      .add_startup_system(setup_customer_tilemap.in_set(TileSetupSystemSet::SpawnTilemaps))
+      .add_startup_system(place_saved_customers.in_set(TileSetupSystemSet::InsertTileData))
+      .add_systems(
+        (move_packages, sample_conveyor_loads, produce_packages)
+          .chain()
+          .in_set(SimulationSystemSet::Update)
+          .in_schedule(SimulationSchedule)
+      )
+      .add_systems(
+        (
+          issue_orders,
+          fulfil_orders,
+          fail_overdue_orders,
+        )
+          .chain()
+          .after(SimulationSystemSet::Update)
+          .before(SimulationSystemSet::Statistics)
+          .in_schedule(SimulationSchedule)
+      );
+
+    if self.include_textures {
+      app
+        .init_resource::<PackageSprites>()
+        .add_startup_system(insert_customer_texture.in_set(TileSetupSystemSet::InsertTileData))
+        .add_startup_system(setup_package_sprites)
+        .add_system(update_package_sprites.in_set(GameSystemSet::PostTilePlacing).after(run_simulation_schedule));
+    }
+  }
+}
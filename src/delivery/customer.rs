@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::tile::prelude::*;
+
+pub mod prelude {
+  pub use super::Customer;
+  pub use super::CustomerTileLayer;
+}
+
+pub mod plugin_exports {
+  pub use super::insert_customer_texture;
+  pub use super::place_saved_customers;
+  pub use super::setup_customer_tilemap;
+}
+
+const CUSTOMER_TEXTURE_INDEX: u32 = 1;
+
+#[derive(Debug, Component)]
+pub struct CustomerTileLayer;
+
+// A building which orders packages and accepts them from conveyors pointing into it.
+#[derive(Debug, Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Customer {
+  pub next_order_tick: u64,
+}
+
+pub fn setup_customer_tilemap(
+  playfield_size: Res<PlayfieldSize>,
+  mut commands: Commands,
+) {
+  let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
+  let grid_size = tile_size.into();
+  let map_type = TilemapType::Square;
+
+  let customer_tilemap = commands.spawn_empty().id();
+  let customer_map_size = TilemapSize {
+    x: playfield_size.0.x,
+    y: playfield_size.0.y,
+  };
+
+  commands
+    .entity(customer_tilemap)
+    .insert(TilemapBundle {
+      grid_size,
+      map_type,
+      size: customer_map_size,
+      storage: TileStorage::empty(customer_map_size),
+      tile_size,
+      transform: get_tilemap_center_transform(&customer_map_size, &grid_size, &map_type, 5.0),
+      ..default()
+    })
+    .insert(CustomerTileLayer);
+}
+
+pub fn insert_customer_texture(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  customer_layer: Query<(Entity, &CustomerTileLayer)>,
+) {
+  let Ok((customer_entity, _)) = customer_layer.get_single() else {
+    error!(
+      "Tilemap query for the customer layer returned {} items when it only should have returned 1.",
+      customer_layer.iter().len(),
+    );
+    return;
+  };
+
+  commands.entity(customer_entity).insert(TilemapTexture::Single(asset_server.load("tiles2.png")));
+}
+
+pub fn place_saved_customers(
+  mut commands: Commands,
+  saved_layout: Option<Res<SavedLayout>>,
+  mut customer_layer: Query<(Entity, &mut TileStorage, &CustomerTileLayer)>,
+) {
+  let Some(saved_layout) = saved_layout else { return; };
+  let Ok((customer_entity, mut customer_storage, _)) = customer_layer.get_single_mut() else {
+    error!(
+      "Tilemap query for the customer layer returned {} items when it only should have returned 1.",
+      customer_layer.iter().len(),
+    );
+    return;
+  };
+
+  for customer in saved_layout.customers.iter() {
+    let position = TilePos { x: customer.x, y: customer.y };
+    if !position.within_map_bounds(&customer_storage.size) {
+      warn!("Saved customer at ({}, {}) is outside of the playfield", customer.x, customer.y);
+      continue;
+    }
+    let tile_entity = commands
+      .spawn(TileBundle {
+        position,
+        tilemap_id: TilemapId(customer_entity),
+        texture_index: TileTextureIndex(CUSTOMER_TEXTURE_INDEX),
+        ..default()
+      })
+      .insert(Customer { next_order_tick: customer.first_order_tick })
+      .id();
+    customer_storage.set(&position, tile_entity);
+  }
+}
@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+  simulation::{prelude::*, SIMULATION_TICK_LENGTH},
+  stats::{PackageEvent, PackageKind, PackageOutcome},
+};
+
+use super::customer::Customer;
+
+pub mod prelude {
+  pub use super::DeliveryScore;
+  pub use super::Orders;
+}
+
+pub mod plugin_exports {
+  pub use super::fail_overdue_orders;
+  pub use super::fulfil_orders;
+  pub use super::issue_orders;
+  pub use super::DeliveryScore;
+  pub use super::OrderEvent;
+  pub use super::Orders;
+  pub use super::PackageDelivered;
+}
+
+// Number of different package kinds customers order.
+pub const PACKAGE_KIND_COUNT: u32 = 3;
+// Ticks between a customer's orders.
+pub const ORDER_INTERVAL_TICKS: u64 = 600;
+// Ticks a customer gives the player to fill an order.
+pub const ORDER_DURATION_TICKS: u64 = 1200;
+// Ticks past the deadline after which a customer gives up on an order.
+pub const ORDER_GRACE_TICKS: u64 = 600;
+pub const MAX_ORDER_QUANTITY: u32 = 5;
+
+pub const ORDER_REWARD_PER_PACKAGE: i64 = 20;
+// Taken off the reward for every second an order is late.
+pub const LATE_PENALTY_PER_SECOND: i64 = 2;
+pub const FAILED_ORDER_PENALTY: i64 = 50;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Order {
+  pub id: u32,
+  pub customer: Entity,
+  pub customer_pos: TilePos,
+  pub kind: PackageKind,
+  pub quantity: u32,
+  pub delivered: u32,
+  pub issued_tick: u64,
+  pub deadline_tick: u64,
+}
+
+impl Order {
+  pub fn is_filled(&self) -> bool {
+    self.delivered >= self.quantity
+  }
+
+  // Ticks left until the deadline, negative once the order is late.
+  pub fn ticks_remaining(&self, tick: u64) -> i64 {
+    self.deadline_tick as i64 - tick as i64
+  }
+
+  pub fn seconds_remaining(&self, tick: u64) -> f32 {
+    self.ticks_remaining(tick) as f32 * SIMULATION_TICK_LENGTH.as_secs_f32()
+  }
+
+  pub fn reward(&self, tick: u64) -> i64 {
+    let late_seconds = (-self.seconds_remaining(tick)).max(0.0) as i64;
+    (ORDER_REWARD_PER_PACKAGE * self.quantity as i64 - late_seconds * LATE_PENALTY_PER_SECOND).max(0)
+  }
+}
+
+#[derive(Debug, Resource, Default)]
+pub struct Orders {
+  pub active: Vec<Order>,
+  next_id: u32,
+}
+
+#[derive(Debug, Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct DeliveryScore {
+  pub score: i64,
+  pub completed: u32,
+  pub failed: u32,
+}
+
+// Sent when a package is handed off to a customer.
+#[derive(Debug, Clone)]
+pub struct PackageDelivered {
+  pub customer: Entity,
+  pub kind: PackageKind,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OrderOutcome {
+  Issued,
+  Completed { reward: i64, late: bool },
+  Failed { penalty: i64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderEvent {
+  pub order: Order,
+  pub outcome: OrderOutcome,
+}
+
+// Small deterministic hash so headless runs of the same layout always produce the same orders.
+fn order_seed(pos: &TilePos, tick: u64) -> u64 {
+  let mut seed = tick ^ ((pos.x as u64) << 32) ^ (pos.y as u64);
+  seed ^= seed >> 33;
+  seed = seed.wrapping_mul(0xff51afd7ed558ccd);
+  seed ^= seed >> 33;
+  seed = seed.wrapping_mul(0xc4ceb9fe1a85ec53);
+  seed ^ (seed >> 33)
+}
+
+pub fn issue_orders(
+  simulation_time: Res<SimulationTime>,
+  mut customers: Query<(Entity, &TilePos, &mut Customer)>,
+  mut orders: ResMut<Orders>,
+  mut order_events: EventWriter<OrderEvent>,
+) {
+  let tick = simulation_time.tick();
+  for (customer_entity, customer_pos, mut customer) in customers.iter_mut() {
+    if tick < customer.next_order_tick || orders.active.iter().any(|order| order.customer == customer_entity) {
+      continue;
+    }
+
+    let seed = order_seed(customer_pos, tick);
+    let order = Order {
+      id: orders.next_id,
+      customer: customer_entity,
+      customer_pos: *customer_pos,
+      kind: PackageKind((seed % PACKAGE_KIND_COUNT as u64) as u32),
+      quantity: 1 + ((seed >> 16) % MAX_ORDER_QUANTITY as u64) as u32,
+      delivered: 0,
+      issued_tick: tick,
+      deadline_tick: tick + ORDER_DURATION_TICKS,
+    };
+    orders.next_id += 1;
+    customer.next_order_tick = tick + ORDER_INTERVAL_TICKS;
+    order_events.send(OrderEvent { order: order.clone(), outcome: OrderOutcome::Issued });
+    orders.active.push(order);
+  }
+}
+
+pub fn fulfil_orders(
+  simulation_time: Res<SimulationTime>,
+  mut deliveries: EventReader<PackageDelivered>,
+  mut orders: ResMut<Orders>,
+  mut score: ResMut<DeliveryScore>,
+  mut order_events: EventWriter<OrderEvent>,
+  mut package_events: EventWriter<PackageEvent>,
+) {
+  let tick = simulation_time.tick();
+  for delivery in deliveries.iter() {
+    // the oldest matching order is filled first
+    let order = orders
+      .active
+      .iter_mut()
+      .find(|order| order.customer == delivery.customer && order.kind == delivery.kind && !order.is_filled());
+    let Some(order) = order else {
+      package_events.send(PackageEvent { kind: delivery.kind, outcome: PackageOutcome::Lost });
+      continue;
+    };
+    order.delivered += 1;
+    package_events.send(PackageEvent { kind: delivery.kind, outcome: PackageOutcome::Delivered });
+  }
+
+  let (filled, active): (Vec<_>, Vec<_>) = orders.active.drain(..).partition(Order::is_filled);
+  orders.active = active;
+  for order in filled {
+    let reward = order.reward(tick);
+    score.score += reward;
+    score.completed += 1;
+    let late = order.ticks_remaining(tick) < 0;
+    order_events.send(OrderEvent { order, outcome: OrderOutcome::Completed { reward, late } });
+  }
+}
+
+pub fn fail_overdue_orders(
+  simulation_time: Res<SimulationTime>,
+  mut orders: ResMut<Orders>,
+  mut score: ResMut<DeliveryScore>,
+  mut order_events: EventWriter<OrderEvent>,
+) {
+  let tick = simulation_time.tick();
+  let (overdue, active): (Vec<_>, Vec<_>) = orders
+    .active
+    .drain(..)
+    .partition(|order| order.ticks_remaining(tick) < -(ORDER_GRACE_TICKS as i64));
+  orders.active = active;
+  for order in overdue {
+    score.score -= FAILED_ORDER_PENALTY;
+    score.failed += 1;
+    order_events.send(OrderEvent { order, outcome: OrderOutcome::Failed { penalty: FAILED_ORDER_PENALTY } });
+  }
+}
+
+#[cfg(test)]
+mod orders_test {
+  use super::*;
+
+  fn order(quantity: u32, deadline_tick: u64) -> Order {
+    Order {
+      id: 0,
+      customer: Entity::from_raw(0),
+      customer_pos: TilePos { x: 0, y: 0 },
+      kind: PackageKind(0),
+      quantity,
+      delivered: 0,
+      issued_tick: 0,
+      deadline_tick,
+    }
+  }
+
+  #[test]
+  fn on_time_reward() {
+    assert_eq!(order(3, 100).reward(50), 3 * ORDER_REWARD_PER_PACKAGE);
+  }
+
+  #[test]
+  fn late_penalty() {
+    let ticks_per_second = (1.0 / SIMULATION_TICK_LENGTH.as_secs_f32()) as u64;
+    let late = order(3, 100).reward(100 + ticks_per_second * 5);
+    assert_eq!(late, 3 * ORDER_REWARD_PER_PACKAGE - 5 * LATE_PENALTY_PER_SECOND);
+    assert_eq!(order(1, 0).reward(ticks_per_second * 1000), 0);
+  }
+
+  #[test]
+  fn order_seeds_are_deterministic() {
+    let pos = TilePos { x: 3, y: 7 };
+    assert_eq!(order_seed(&pos, 600), order_seed(&pos, 600));
+    assert_ne!(order_seed(&pos, 600), order_seed(&pos, 1200));
+  }
+}
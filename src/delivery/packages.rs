@@ -0,0 +1,392 @@
+use bevy::{
+  prelude::*,
+  render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+  utils::{HashMap, HashSet},
+};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+  stats::{ConveyorLoadSample, PackageEvent, PackageKind, PackageOutcome, PackageShape},
+  tile::{network::*, prelude::*},
+};
+
+use super::{customer::CustomerTileLayer, orders::{Orders, PackageDelivered}};
+
+pub mod plugin_exports {
+  pub use super::move_packages;
+  pub use super::produce_packages;
+  pub use super::sample_conveyor_loads;
+  pub use super::setup_package_sprites;
+  pub use super::update_package_sprites;
+  pub use super::PackageSprites;
+}
+
+// Ticks a package takes to cross a single plain conveyor. Faster belts cut this down.
+pub const TICKS_PER_TILE: u32 = 10;
+const PACKAGE_SPRITE_SIZE: u32 = 8;
+const PACKAGE_FILL: [u8; 4] = [255, 255, 255, 255];
+const PACKAGE_OUTLINE: [u8; 4] = [0, 0, 0, 255];
+// Between the conveyor and direction arrow layers.
+const PACKAGE_Z: f32 = 12.0;
+
+// A package travelling along the conveyors towards the customer it was sent out for.
+#[derive(Debug, Component, Clone)]
+pub struct Package {
+  pub kind: PackageKind,
+  pub customer: Entity,
+  pub pos: TilePos,
+  // How far across the current conveyor the package is, up to `TICKS_PER_TILE`.
+  pub progress: u32,
+}
+
+impl Package {
+  pub fn is_ready_to_move(&self) -> bool {
+    self.progress >= TICKS_PER_TILE
+  }
+}
+
+// The customer which the line of conveyors starting at `pos` leads into, if any.
+fn line_destination(
+  tile_storage: &TileStorage,
+  directions: &impl Fn(Entity) -> Option<ConveyorDirection>,
+  customer_storage: &TileStorage,
+  pos: TilePos,
+) -> Option<Entity> {
+  let mut visited = HashSet::new();
+  let mut current = pos;
+  while visited.insert(current) {
+    match conveyor_output(tile_storage, directions, &current)? {
+      ConveyorOutput::Connected(next) => current = next,
+      ConveyorOutput::Empty(target) => return customer_storage.get(&target),
+      ConveyorOutput::OffPlayfield | ConveyorOutput::HeadOn(_) => return None,
+    }
+  }
+  None
+}
+
+// Puts a package on the first conveyor of every line leading into a customer, for as
+// long as the customer's order needs more packages than are already on their way.
+pub fn produce_packages(
+  mut commands: Commands,
+  tilemap: Query<&TileStorage, With<ConveyorTileLayer>>,
+  customer_layer: Query<&TileStorage, With<CustomerTileLayer>>,
+  conveyor_directions: Query<&ConveyorDirection>,
+  orders: Res<Orders>,
+  packages: Query<&Package>,
+  mut package_events: EventWriter<PackageEvent>,
+) {
+  let (Ok(tile_storage), Ok(customer_storage)) = (tilemap.get_single(), customer_layer.get_single()) else { return; };
+  let directions = |entity: Entity| conveyor_directions.get(entity).ok().copied();
+
+  let conveyors: Vec<_> = tile_positions(&tile_storage.size)
+    .filter(|pos| conveyor_direction_at(tile_storage, &directions, pos).is_some())
+    .collect();
+  let fed: HashSet<_> = conveyors
+    .iter()
+    .filter_map(|pos| match conveyor_output(tile_storage, &directions, pos) {
+      Some(ConveyorOutput::Connected(target)) => Some(target),
+      _ => None,
+    })
+    .collect();
+  let occupied: HashSet<_> = packages.iter().map(|package| package.pos).collect();
+  let mut on_their_way: HashMap<(Entity, PackageKind), u32> = HashMap::new();
+  for package in packages.iter() {
+    *on_their_way.entry((package.customer, package.kind)).or_default() += 1;
+  }
+
+  for head in conveyors.iter().filter(|pos| !fed.contains(pos) && !occupied.contains(pos)) {
+    let Some(customer) = line_destination(tile_storage, &directions, customer_storage, *head) else { continue; };
+    let order = orders.active.iter().find(|order| {
+      let sent = on_their_way.get(&(customer, order.kind)).copied().unwrap_or_default();
+      order.customer == customer && order.delivered + sent < order.quantity
+    });
+    let Some(order) = order else { continue; };
+
+    *on_their_way.entry((customer, order.kind)).or_default() += 1;
+    commands.spawn(Package { kind: order.kind, customer, pos: *head, progress: 0 });
+    package_events.send(PackageEvent { kind: order.kind, outcome: PackageOutcome::Produced });
+  }
+}
+
+// Moves every package which has crossed its conveyor on to the next one, handing it to
+// the customer at the end of the line. Packages wait while the next conveyor is taken.
+pub fn move_packages(
+  mut commands: Commands,
+  tilemap: Query<&TileStorage, With<ConveyorTileLayer>>,
+  customer_layer: Query<&TileStorage, With<CustomerTileLayer>>,
+  conveyors: Query<(&ConveyorDirection, &TileType)>,
+  mut packages: Query<(Entity, &mut Package)>,
+  mut deliveries: EventWriter<PackageDelivered>,
+  mut package_events: EventWriter<PackageEvent>,
+) {
+  let (Ok(tile_storage), Ok(customer_storage)) = (tilemap.get_single(), customer_layer.get_single()) else { return; };
+  let directions = |entity: Entity| conveyors.get(entity).ok().map(|(direction, _)| *direction);
+
+  let mut occupied = HashMap::new();
+  let mut waiting = Vec::new();
+  for (entity, mut package) in packages.iter_mut() {
+    // packages fall off conveyors which are removed from under them
+    if conveyor_direction_at(tile_storage, &directions, &package.pos).is_none() {
+      commands.entity(entity).despawn();
+      package_events.send(PackageEvent { kind: package.kind, outcome: PackageOutcome::Lost });
+      continue;
+    }
+    occupied.insert(package.pos, entity);
+    let belt_speed = tile_storage.get(&package.pos).and_then(|tile| conveyors.get(tile).ok()).map_or(1, |(_, tile_type)| tile_type.belt_speed());
+    package.progress = (package.progress + belt_speed).min(TICKS_PER_TILE);
+    if package.is_ready_to_move() {
+      waiting.push(entity);
+    }
+  }
+
+  // keep going until nothing moves, so a whole line of packages moves up together
+  // no matter which order they're visited in
+  loop {
+    let waiting_before = waiting.len();
+    waiting.retain(|entity| {
+      let Ok((_, mut package)) = packages.get_mut(*entity) else { return false; };
+      match conveyor_output(tile_storage, &directions, &package.pos) {
+        Some(ConveyorOutput::Connected(next)) if !occupied.contains_key(&next) => {
+          occupied.remove(&package.pos);
+          occupied.insert(next, *entity);
+          package.pos = next;
+          package.progress = 0;
+          false
+        }
+        Some(ConveyorOutput::Empty(target)) => {
+          let Some(customer) = customer_storage.get(&target) else { return true; };
+          occupied.remove(&package.pos);
+          commands.entity(*entity).despawn();
+          deliveries.send(PackageDelivered { customer, kind: package.kind });
+          false
+        }
+        _ => true,
+      }
+    });
+    if waiting.len() == waiting_before {
+      break;
+    }
+  }
+}
+
+pub fn sample_conveyor_loads(
+  tilemap: Query<&TileStorage, With<ConveyorTileLayer>>,
+  conveyor_directions: Query<&ConveyorDirection>,
+  packages: Query<&Package>,
+  mut load_samples: EventWriter<ConveyorLoadSample>,
+) {
+  let Ok(tile_storage) = tilemap.get_single() else { return; };
+  let directions = |entity: Entity| conveyor_directions.get(entity).ok().copied();
+
+  let occupied: HashSet<_> = packages.iter().map(|package| package.pos).collect();
+  for pos in tile_positions(&tile_storage.size) {
+    if conveyor_direction_at(tile_storage, &directions, &pos).is_some() {
+      load_samples.send(ConveyorLoadSample { pos, occupied: occupied.contains(&pos) });
+    }
+  }
+}
+
+// One outlined shape per `PackageShape`, side by side, which package sprites tint in
+// their kind's colour.
+#[derive(Debug, Resource, Default)]
+pub struct PackageSprites {
+  atlas: Handle<TextureAtlas>,
+}
+
+fn shape_frame(shape: PackageShape) -> usize {
+  PackageShape::VALUES.iter().position(|value| *value == shape).unwrap_or_default()
+}
+
+fn shape_pixel(shape: PackageShape, x: i32, y: i32) -> bool {
+  if !(0..PACKAGE_SPRITE_SIZE as i32).contains(&x) || !(0..PACKAGE_SPRITE_SIZE as i32).contains(&y) {
+    return false;
+  }
+  // image rows run downwards, so triangles with a growing y point up
+  let half = PACKAGE_SPRITE_SIZE as f32 / 2.0;
+  let position = Vec2::new(x as f32 + 0.5 - half, y as f32 + 0.5 - half);
+  match shape {
+    PackageShape::Circle => position.length() <= 3.0,
+    PackageShape::Square => position.x.abs() <= 2.5 && position.y.abs() <= 2.5,
+    PackageShape::Triangle => position.y.abs() <= 2.5 && position.x.abs() <= 0.5 + (position.y + 2.5) * 0.4,
+    PackageShape::Diamond => position.x.abs() + position.y.abs() <= 3.0,
+  }
+}
+
+fn package_image_data() -> Vec<u8> {
+  let size = PACKAGE_SPRITE_SIZE as i32;
+  let width = size * PackageShape::VALUES.len() as i32;
+  let mut data = vec![0; (width * size * 4) as usize];
+  for (frame, shape) in PackageShape::VALUES.iter().enumerate() {
+    for y in 0..size {
+      for x in 0..size {
+        let touches_shape = (-1..=1).any(|dy| (-1..=1).any(|dx| shape_pixel(*shape, x + dx, y + dy)));
+        let color = match (shape_pixel(*shape, x, y), touches_shape) {
+          (true, _) => PACKAGE_FILL,
+          (false, true) => PACKAGE_OUTLINE,
+          (false, false) => continue,
+        };
+        let offset = ((y * width + frame as i32 * size + x) * 4) as usize;
+        data[offset..offset + 4].copy_from_slice(&color);
+      }
+    }
+  }
+  data
+}
+
+pub fn setup_package_sprites(
+  mut images: ResMut<Assets<Image>>,
+  mut atlases: ResMut<Assets<TextureAtlas>>,
+  mut package_sprites: ResMut<PackageSprites>,
+) {
+  let frames = PackageShape::VALUES.len();
+  let image = images.add(Image::new(
+    Extent3d { width: PACKAGE_SPRITE_SIZE * frames as u32, height: PACKAGE_SPRITE_SIZE, depth_or_array_layers: 1 },
+    TextureDimension::D2,
+    package_image_data(),
+    TextureFormat::Rgba8UnormSrgb,
+  ));
+  let atlas = TextureAtlas::from_grid(image, Vec2::splat(PACKAGE_SPRITE_SIZE as f32), frames, 1, None, None);
+  package_sprites.atlas = atlases.add(atlas);
+}
+
+// Draws packages as small shapes in their kind's colour, sliding across their conveyor.
+// The shapes tell kinds apart without relying on the colours.
+pub fn update_package_sprites(
+  mut commands: Commands,
+  package_sprites: Res<PackageSprites>,
+  tilemap: Query<(&TileStorage, &TilemapGridSize, &TilemapType, &GlobalTransform), With<ConveyorTileLayer>>,
+  conveyor_directions: Query<&ConveyorDirection>,
+  mut packages: Query<(Entity, &Package, Option<&mut Transform>)>,
+) {
+  let Ok((tile_storage, grid_size, map_type, tilemap_transform)) = tilemap.get_single() else { return; };
+  let directions = |entity: Entity| conveyor_directions.get(entity).ok().copied();
+
+  for (entity, package, transform) in packages.iter_mut() {
+    let center = tilemap_transform.transform_point(package.pos.center_in_world(grid_size, map_type).extend(0.0)).truncate();
+    let forward = conveyor_direction_at(tile_storage, &directions, &package.pos).map_or(Vec2::ZERO, |direction| direction.offset().as_vec2());
+    // packages enter a conveyor at its back edge and wait at its front edge
+    let travelled = package.progress as f32 / TICKS_PER_TILE as f32 - 0.5;
+    let translation = (center + forward * travelled * grid_size.x).round().extend(PACKAGE_Z);
+
+    match transform {
+      Some(mut transform) => transform.translation = translation,
+      None => {
+        let [red, green, blue] = package.kind.color();
+        commands.entity(entity).insert(SpriteSheetBundle {
+          texture_atlas: package_sprites.atlas.clone(),
+          sprite: TextureAtlasSprite {
+            index: shape_frame(package.kind.shape()),
+            color: Color::rgb_u8(red, green, blue),
+            ..default()
+          },
+          transform: Transform::from_translation(translation),
+          ..default()
+        });
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod packages_test {
+  use crate::{
+    delivery::{orders::ORDER_INTERVAL_TICKS, prelude::*},
+    headless::headless_app,
+    input::{chained_tile::{ChainedTileChangeEvent, ChainedTileChangePosition, ChainedTileChangeType, ChainedTilePlaceDirection}, prelude::*},
+    simulation::{prelude::*, run_simulation_ticks},
+    stats::prelude::*,
+    tile::save::SavedCustomer,
+  };
+
+  use super::*;
+
+  // An app with a line of three `tile_type` belts leading east into a customer.
+  fn belt_app(tile_type: TileType) -> App {
+    let mut app = headless_app(SavedLayout {
+      playfield_size: UVec2::new(4, 4),
+      tiles: vec![],
+      customers: vec![SavedCustomer { x: 3, y: 0, first_order_tick: 0 }],
+      starting_money: None,
+      inventory: None,
+    });
+    app.setup();
+    app.world.resource_mut::<SimulationTime>().paused = true;
+    app.update();
+
+    app.world.resource_mut::<SelectedTileDirection>().direction = ConveyorDirection::East;
+    app.world.send_event(ChainedTileChangeEvent {
+      position: ChainedTileChangePosition::StraightLine { start: IVec2::new(-1, 0), end: IVec2::new(2, 0) },
+      change_type: ChainedTileChangeType::Put { tile_type, chain: false, direction: ChainedTilePlaceDirection::Normal },
+    });
+    app.update();
+    app
+  }
+
+  #[test]
+  fn belt_into_customer_completes_order() {
+    let mut app = belt_app(TileType::Conveyor);
+    run_simulation_ticks(&mut app.world, ORDER_INTERVAL_TICKS);
+
+    let score = app.world.resource::<DeliveryScore>();
+    assert_eq!(score.completed, 1);
+    assert!(score.score > 0);
+    assert_eq!(app.world.query::<&Package>().iter(&app.world).count(), 0);
+
+    let stats = app.world.resource::<ThroughputStats>();
+    let delivered: u32 = stats.totals().values().map(|counts| counts.delivered).sum();
+    assert!(delivered > 0);
+    let first_window = stats.history().next().unwrap();
+    assert_eq!(first_window.conveyors.len(), 3);
+    assert!(first_window.conveyors.values().all(|samples| samples.occupied > 0));
+  }
+
+  #[test]
+  fn package_kinds_get_their_own_shapes() {
+    let mut app = belt_app(TileType::Conveyor);
+    app.init_resource::<PackageSprites>().add_system(update_package_sprites);
+    let customer = Entity::from_raw(0);
+    let first = app.world.spawn(Package { kind: PackageKind(0), customer, pos: TilePos { x: 0, y: 0 }, progress: 0 }).id();
+    let second = app.world.spawn(Package { kind: PackageKind(1), customer, pos: TilePos { x: 1, y: 0 }, progress: 0 }).id();
+    app.update();
+
+    let index = |entity: Entity| app.world.get::<TextureAtlasSprite>(entity).unwrap().index;
+    assert_eq!(index(first), shape_frame(PackageKind(0).shape()));
+    assert_eq!(index(second), shape_frame(PackageKind(1).shape()));
+    assert_ne!(index(first), index(second));
+  }
+
+  #[test]
+  fn package_shapes_are_outlined() {
+    let data = package_image_data();
+    let width = PACKAGE_SPRITE_SIZE * PackageShape::VALUES.len() as u32;
+    let pixel = |x: u32, y: u32| &data[((y * width + x) * 4) as usize..((y * width + x) * 4 + 4) as usize];
+    for shape in PackageShape::VALUES {
+      let left = shape_frame(shape) as u32 * PACKAGE_SPRITE_SIZE;
+      assert_eq!(pixel(left + 4, 4), PACKAGE_FILL, "{shape:?}");
+    }
+    // no two shapes fill the same pixels
+    let filled = |shape: PackageShape| -> Vec<_> {
+      (0..PACKAGE_SPRITE_SIZE as i32).flat_map(|y| (0..PACKAGE_SPRITE_SIZE as i32).map(move |x| shape_pixel(shape, x, y))).collect()
+    };
+    let shapes: HashSet<_> = PackageShape::VALUES.into_iter().map(filled).collect();
+    assert_eq!(shapes.len(), PackageShape::VALUES.len());
+  }
+
+  #[test]
+  fn faster_belts_deliver_sooner() {
+    let ticks_to_deliver = |tile_type: TileType| {
+      let mut app = belt_app(tile_type);
+      (1..=ORDER_INTERVAL_TICKS)
+        .find(|_| {
+          run_simulation_ticks(&mut app.world, 1);
+          app.world.resource::<ThroughputStats>().totals().values().any(|counts| counts.delivered > 0)
+        })
+        .unwrap()
+    };
+    let plain = ticks_to_deliver(TileType::Conveyor);
+    let fast = ticks_to_deliver(TileType::FastConveyor);
+    let express = ticks_to_deliver(TileType::ExpressConveyor);
+    assert!(fast < plain);
+    assert!(express < fast);
+  }
+}
@@ -0,0 +1,283 @@
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+  delivery::orders::{fulfil_orders, OrderEvent, OrderOutcome},
+  simulation::prelude::*,
+  tile::{placement::{PlacementRejected, RejectionReason}, prelude::*},
+};
+
+pub mod prelude {
+  pub use super::EconomyPlugin;
+  pub use super::TileInventory;
+  pub use super::Wallet;
+}
+
+// Money the player starts with when the loaded level doesn't set a budget.
+pub const DEFAULT_STARTING_MONEY: i64 = 200;
+pub const CONVEYOR_COST: i64 = 5;
+pub const FAST_CONVEYOR_COST: i64 = 12;
+pub const EXPRESS_CONVEYOR_COST: i64 = 25;
+
+// Price of building a tile of the given type. Removing a tile refunds the full price.
+pub fn tile_cost(tile_type: TileType) -> i64 {
+  match tile_type {
+    TileType::Conveyor => CONVEYOR_COST,
+    TileType::FastConveyor => FAST_CONVEYOR_COST,
+    TileType::ExpressConveyor => EXPRESS_CONVEYOR_COST,
+  }
+}
+
+// Price of building `tile_type` on a spot which might already hold a tile, which is
+// refunded as it gets replaced.
+pub fn placement_cost(tile_type: TileType, replaced: Option<TileType>) -> i64 {
+  tile_cost(tile_type) - replaced.map_or(0, tile_cost)
+}
+
+#[derive(Debug, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Wallet {
+  balance: i64,
+}
+
+impl Default for Wallet {
+  fn default() -> Self {
+    Wallet::new(DEFAULT_STARTING_MONEY)
+  }
+}
+
+impl Wallet {
+  pub fn new(balance: i64) -> Wallet {
+    Wallet { balance }
+  }
+
+  pub fn balance(&self) -> i64 {
+    self.balance
+  }
+
+  pub fn can_afford(&self, cost: i64) -> bool {
+    cost <= self.balance
+  }
+
+  // Takes `cost` out of the wallet, leaving it untouched when there isn't enough money.
+  pub fn try_spend(&mut self, cost: i64) -> bool {
+    if !self.can_afford(cost) {
+      return false;
+    }
+    self.balance -= cost;
+    true
+  }
+
+  pub fn earn(&mut self, amount: i64) {
+    self.balance += amount;
+  }
+}
+
+// The tiles left to build in puzzle levels, which hand out a fixed number of each type.
+#[derive(Debug, Resource, Default, Clone)]
+pub struct TileInventory {
+  remaining: HashMap<TileType, u32>,
+}
+
+impl TileInventory {
+  pub fn new(remaining: HashMap<TileType, u32>) -> TileInventory {
+    TileInventory { remaining }
+  }
+
+  pub fn remaining(&self, tile_type: TileType) -> u32 {
+    self.remaining.get(&tile_type).copied().unwrap_or(0)
+  }
+
+  // Building over a tile of the same type hands that tile back, so it's always possible.
+  pub fn can_build(&self, tile_type: TileType, replaced: Option<TileType>) -> bool {
+    self.remaining(tile_type) > 0 || replaced == Some(tile_type)
+  }
+
+  pub fn build(&mut self, tile_type: TileType, replaced: Option<TileType>) {
+    if let Some(replaced) = replaced {
+      self.restore(replaced);
+    }
+    let remaining = self.remaining.entry(tile_type).or_default();
+    *remaining = remaining.saturating_sub(1);
+  }
+
+  pub fn restore(&mut self, tile_type: TileType) {
+    *self.remaining.entry(tile_type).or_default() += 1;
+  }
+}
+
+// Marks tiles the player paid for, as opposed to the ones the level starts with, which
+// can't be sold back.
+#[derive(Debug, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Purchased;
+
+// Pays for tiles as they're built and hands back their cost when they're removed, out
+// of the wallet and the inventory, whichever the level has. Building is free in apps
+// without either, like the tests which don't add the economy.
+#[derive(SystemParam)]
+pub struct BuildBudget<'w, 's> {
+  wallet: Option<ResMut<'w, Wallet>>,
+  inventory: Option<ResMut<'w, TileInventory>>,
+  rejected_placements: EventWriter<'w, PlacementRejected>,
+  tile_types: Query<'w, 's, (&'static TileType, Option<&'static Purchased>)>,
+}
+
+impl<'w, 's> BuildBudget<'w, 's> {
+  // The type of a tile the player bought, which is what can be refunded.
+  fn purchased_tile_type(&self, tile_entity: Entity) -> Option<TileType> {
+    match self.tile_types.get(tile_entity) {
+      Ok((&tile_type, Some(_))) => Some(tile_type),
+      _ => None,
+    }
+  }
+
+  // Pays for building `tile_type` at `pos`, less the refund for a bought tile it replaces,
+  // returning false and telling the player why when the budget doesn't allow it.
+  pub fn try_build(&mut self, tile_storage: &TileStorage, pos: TilePos, tile_type: TileType) -> bool {
+    let replaced = tile_storage.get(&pos).and_then(|entity| self.purchased_tile_type(entity));
+    let cost = placement_cost(tile_type, replaced);
+    let rejection = match (self.wallet.as_deref(), self.inventory.as_deref()) {
+      (Some(wallet), _) if !wallet.can_afford(cost) => {
+        Some(RejectionReason::CannotAfford { cost, balance: wallet.balance() })
+      }
+      (_, Some(inventory)) if !inventory.can_build(tile_type, replaced) => Some(RejectionReason::OutOfTiles(tile_type)),
+      _ => None,
+    };
+    if let Some(reason) = rejection {
+      self.rejected_placements.send(PlacementRejected { pos, reason });
+      return false;
+    }
+
+    if let Some(wallet) = self.wallet.as_mut() {
+      wallet.try_spend(cost);
+    }
+    if let Some(inventory) = self.inventory.as_mut() {
+      inventory.build(tile_type, replaced);
+    }
+    true
+  }
+
+  // Hands back the cost of a tile which is about to be removed, if the player bought it.
+  pub fn refund(&mut self, tile_entity: Entity) {
+    let Some(tile_type) = self.purchased_tile_type(tile_entity) else { return; };
+    if let Some(wallet) = self.wallet.as_mut() {
+      wallet.earn(tile_cost(tile_type));
+    }
+    if let Some(inventory) = self.inventory.as_mut() {
+      inventory.restore(tile_type);
+    }
+  }
+}
+
+// Levels with an inventory but no starting money are built from the inventory alone.
+pub fn set_level_budget(
+  mut commands: Commands,
+  saved_layout: Option<Res<SavedLayout>>,
+) {
+  let Some(saved_layout) = saved_layout else { return; };
+  match (saved_layout.starting_money, saved_layout.inventory.clone()) {
+    (Some(starting_money), inventory) => {
+      commands.insert_resource(Wallet::new(starting_money));
+      if let Some(inventory) = inventory {
+        commands.insert_resource(TileInventory::new(inventory));
+      }
+    }
+    (None, Some(inventory)) => {
+      commands.remove_resource::<Wallet>();
+      commands.insert_resource(TileInventory::new(inventory));
+    }
+    (None, None) => {}
+  }
+}
+
+pub fn collect_order_rewards(
+  mut order_events: EventReader<OrderEvent>,
+  wallet: Option<ResMut<Wallet>>,
+) {
+  let Some(mut wallet) = wallet else { return; };
+  for order_event in order_events.iter() {
+    if let OrderOutcome::Completed { reward, .. } = order_event.outcome {
+      wallet.earn(reward);
+    }
+  }
+}
+
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<Wallet>()
+      .add_startup_system(set_level_budget)
+      .add_system(collect_order_rewards.after(fulfil_orders).in_schedule(SimulationSchedule));
+  }
+}
+
+#[cfg(test)]
+mod economy_test {
+  use crate::{
+    headless::headless_app,
+    input::chained_tile::{ChainedTileChangeEvent, ChainedTileChangePosition, ChainedTileChangeType, ChainedTilePlaceDirection},
+    tile::save::SavedTile,
+  };
+
+  use super::*;
+
+  #[test]
+  fn spending() {
+    let mut wallet = Wallet::new(10);
+    assert!(wallet.try_spend(CONVEYOR_COST));
+    assert!(wallet.try_spend(CONVEYOR_COST));
+    assert!(!wallet.try_spend(CONVEYOR_COST));
+    assert_eq!(wallet.balance(), 0);
+    wallet.earn(CONVEYOR_COST);
+    assert_eq!(wallet.balance(), CONVEYOR_COST);
+  }
+
+  #[test]
+  fn replacing_refunds_the_old_tile() {
+    assert_eq!(placement_cost(TileType::Conveyor, None), CONVEYOR_COST);
+    assert_eq!(placement_cost(TileType::Conveyor, Some(TileType::Conveyor)), 0);
+  }
+
+  #[test]
+  fn inventory() {
+    let mut inventory = TileInventory::new(HashMap::from_iter([(TileType::Conveyor, 1)]));
+    assert!(inventory.can_build(TileType::Conveyor, None));
+    inventory.build(TileType::Conveyor, None);
+    assert_eq!(inventory.remaining(TileType::Conveyor), 0);
+    assert!(!inventory.can_build(TileType::Conveyor, None));
+    // replacing a conveyor with another one doesn't use up the inventory
+    assert!(inventory.can_build(TileType::Conveyor, Some(TileType::Conveyor)));
+    inventory.build(TileType::Conveyor, Some(TileType::Conveyor));
+    assert_eq!(inventory.remaining(TileType::Conveyor), 0);
+    inventory.restore(TileType::Conveyor);
+    assert_eq!(inventory.remaining(TileType::Conveyor), 1);
+  }
+
+  #[test]
+  fn only_bought_tiles_are_refunded() {
+    let mut app = headless_app(SavedLayout {
+      playfield_size: UVec2::new(4, 4),
+      tiles: vec![SavedTile { x: 0, y: 0, direction: ConveyorDirection::East, tile_type: TileType::Conveyor }],
+      customers: vec![],
+      starting_money: Some(100),
+      inventory: None,
+    });
+    app.setup();
+    app.update();
+
+    let edit = |app: &mut App, position: IVec2, change_type: ChainedTileChangeType| {
+      app.world.send_event(ChainedTileChangeEvent { position: ChainedTileChangePosition::Single(position), change_type });
+      app.update();
+      app.world.resource::<Wallet>().balance()
+    };
+    let put = || ChainedTileChangeType::Put { tile_type: TileType::Conveyor, chain: false, direction: ChainedTilePlaceDirection::Normal };
+
+    // the level's own tiles can be removed, but aren't worth anything
+    assert_eq!(edit(&mut app, IVec2::ZERO, ChainedTileChangeType::Delete), 100);
+    assert_eq!(edit(&mut app, IVec2::ONE, put()), 100 - CONVEYOR_COST);
+    assert_eq!(edit(&mut app, IVec2::ONE, ChainedTileChangeType::Delete), 100);
+  }
+}
@@ -0,0 +1,302 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+  GameSystemSet,
+  delivery::{orders::PackageDelivered, prelude::*},
+  settings::prelude::*,
+  tile::{prelude::*, update_graphics::systems::conveyor_tile_update_graphics},
+};
+
+pub mod prelude {
+  pub use super::EffectsPlugin;
+}
+
+// Above the conveyor layer.
+const EFFECT_Z: f32 = 20.0;
+const POP_SECONDS: f32 = 0.18;
+// Extra size of a tile when it first pops in.
+const POP_OVERSHOOT: f32 = 0.25;
+const DUST_PARTICLES: u32 = 6;
+const DUST_SECONDS: f32 = 0.35;
+const DUST_SPEED: f32 = 30.0;
+const DUST_COLOR: Color = Color::rgb(0.6, 0.55, 0.5);
+const BURST_PARTICLES: u32 = 12;
+const BURST_SECONDS: f32 = 0.5;
+const BURST_SPEED: f32 = 50.0;
+const BURST_COLOR: Color = Color::rgb(1.0, 0.85, 0.3);
+
+#[derive(Debug, Resource, Default)]
+pub struct EffectAssets {
+  conveyor_atlas: Handle<TextureAtlas>,
+}
+
+// A copy of a freshly placed tile drawn over it, which shrinks down to the tile's size.
+#[derive(Debug, Component)]
+pub struct PlacementPop {
+  tile_size: f32,
+  timer: Timer,
+}
+
+// A single pixel flying off and fading out.
+#[derive(Debug, Component)]
+pub struct Particle {
+  position: Vec2,
+  velocity: Vec2,
+  color: Color,
+  timer: Timer,
+}
+
+// Rounds a size to an even number of pixels, so a sprite centred on a tile keeps its
+// edges on the pixel grid.
+fn pixel_perfect_size(size: f32) -> f32 {
+  (size / 2.0).round() * 2.0
+}
+
+// Single pixel sprites are centred on the middle of a pixel.
+fn pixel_center(position: Vec2) -> Vec2 {
+  (position - 0.5).round() + 0.5
+}
+
+// Cheap deterministic scatter in 0..1, so effects don't need a random number generator.
+fn scatter(seed: u32) -> f32 {
+  let mut value = seed.wrapping_mul(0x9e37_79b9);
+  value ^= value >> 16;
+  value = value.wrapping_mul(0x85eb_ca6b);
+  value ^= value >> 13;
+  (value & 0xffff) as f32 / 0xffff as f32
+}
+
+// Sprite flips and rotation which show an atlas frame the way a tile with `flip` shows it.
+// A diagonal flip mirrors the tile across its anti-diagonal, which sprites can only do as
+// a mirror followed by a quarter turn.
+fn sprite_orientation(flip: &TileFlip) -> (bool, bool, Quat) {
+  match flip.d {
+    false => (flip.x, flip.y, Quat::IDENTITY),
+    true => (!flip.y, flip.x, Quat::from_rotation_z(FRAC_PI_2)),
+  }
+}
+
+fn tile_world_position(pos: &TilePos, grid_size: &TilemapGridSize, map_type: &TilemapType, transform: &Transform) -> Vec2 {
+  transform.transform_point(pos.center_in_world(grid_size, map_type).extend(0.0)).truncate()
+}
+
+fn spawn_particles(commands: &mut Commands, center: Vec2, seed: u32, count: u32, speed: f32, seconds: f32, color: Color) {
+  for index in 0..count {
+    // evenly spread around the circle, each with a bit of jitter
+    let angle = (index as f32 + scatter(seed ^ index)) / count as f32 * TAU;
+    let velocity = Vec2::from_angle(angle) * speed * (0.6 + 0.4 * scatter(seed.rotate_left(7) ^ index));
+    commands.spawn((
+      SpriteBundle {
+        sprite: Sprite { color, custom_size: Some(Vec2::ONE), ..default() },
+        transform: Transform::from_translation(pixel_center(center).extend(EFFECT_Z)),
+        ..default()
+      },
+      Particle { position: center, velocity, color, timer: Timer::from_seconds(seconds, TimerMode::Once) },
+    ));
+  }
+}
+
+pub fn load_effect_assets(
+  asset_server: Res<AssetServer>,
+  mut effect_assets: ResMut<EffectAssets>,
+) {
+  effect_assets.conveyor_atlas = asset_server.load("conveyor.aseprite#atlas");
+}
+
+// Runs once the tile placing systems have applied their commands and picked the tiles'
+// graphics, so a pop shows the new tile rather than the one it replaced.
+pub fn spawn_tile_effects(
+  mut commands: Commands,
+  settings: Res<Settings>,
+  effect_assets: Res<EffectAssets>,
+  mut tile_edits: EventReader<TileEdited>,
+  tilemap: Query<(&TileStorage, &TilemapGridSize, &TilemapTileSize, &TilemapType, &Transform), With<ConveyorTileLayer>>,
+  tiles: Query<(&TileTextureIndex, &TileFlip)>,
+) {
+  if !settings.effects {
+    tile_edits.clear();
+    return;
+  }
+  let Ok((tile_storage, grid_size, tile_size, map_type, transform)) = tilemap.get_single() else { return; };
+
+  for edited in tile_edits.iter() {
+    let center = tile_world_position(&edited.pos, grid_size, map_type, transform);
+    match edited.edit {
+      TileEdit::Placed(_) => {
+        let Some((texture_index, flip)) = tile_storage.get(&edited.pos).and_then(|entity| tiles.get(entity).ok()) else { continue; };
+        let (flip_x, flip_y, rotation) = sprite_orientation(flip);
+        commands.spawn((
+          SpriteSheetBundle {
+            texture_atlas: effect_assets.conveyor_atlas.clone(),
+            sprite: TextureAtlasSprite {
+              index: texture_index.0 as usize,
+              flip_x,
+              flip_y,
+              custom_size: Some(Vec2::splat(pixel_perfect_size(tile_size.x * (1.0 + POP_OVERSHOOT)))),
+              ..default()
+            },
+            transform: Transform::from_translation(center.extend(EFFECT_Z)).with_rotation(rotation),
+            ..default()
+          },
+          PlacementPop { tile_size: tile_size.x, timer: Timer::from_seconds(POP_SECONDS, TimerMode::Once) },
+        ));
+      }
+      TileEdit::Removed => {
+        let seed = edited.pos.x << 16 | edited.pos.y;
+        spawn_particles(&mut commands, center, seed, DUST_PARTICLES, DUST_SPEED, DUST_SECONDS, DUST_COLOR);
+      }
+      TileEdit::Rotated => {}
+    }
+  }
+}
+
+pub fn spawn_delivery_bursts(
+  mut commands: Commands,
+  settings: Res<Settings>,
+  mut deliveries: EventReader<PackageDelivered>,
+  customers: Query<&TilePos, With<Customer>>,
+  customer_layer: Query<(&TilemapGridSize, &TilemapType, &Transform), With<CustomerTileLayer>>,
+) {
+  if !settings.effects {
+    deliveries.clear();
+    return;
+  }
+  let Ok((grid_size, map_type, transform)) = customer_layer.get_single() else { return; };
+
+  for delivery in deliveries.iter() {
+    let Ok(customer_pos) = customers.get(delivery.customer) else { continue; };
+    let center = tile_world_position(customer_pos, grid_size, map_type, transform);
+    let seed = delivery.customer.index() ^ delivery.kind.0.rotate_left(11);
+    spawn_particles(&mut commands, center, seed, BURST_PARTICLES, BURST_SPEED, BURST_SECONDS, BURST_COLOR);
+  }
+}
+
+pub fn animate_placement_pops(
+  mut commands: Commands,
+  time: Res<Time>,
+  mut pops: Query<(Entity, &mut PlacementPop, &mut TextureAtlasSprite)>,
+) {
+  for (entity, mut pop, mut sprite) in pops.iter_mut() {
+    pop.timer.tick(time.delta());
+    if pop.timer.finished() {
+      commands.entity(entity).despawn();
+      continue;
+    }
+    let remaining = 1.0 - pop.timer.percent();
+    let scale = 1.0 + POP_OVERSHOOT * remaining * remaining;
+    sprite.custom_size = Some(Vec2::splat(pixel_perfect_size(pop.tile_size * scale)));
+  }
+}
+
+pub fn animate_particles(
+  mut commands: Commands,
+  time: Res<Time>,
+  mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+  for (entity, mut particle, mut transform, mut sprite) in particles.iter_mut() {
+    particle.timer.tick(time.delta());
+    if particle.timer.finished() {
+      commands.entity(entity).despawn();
+      continue;
+    }
+    let velocity = particle.velocity;
+    particle.position += velocity * time.delta_seconds();
+    transform.translation = pixel_center(particle.position).extend(EFFECT_Z);
+    sprite.color = particle.color.with_a(1.0 - particle.timer.percent());
+  }
+}
+
+// Visual feedback for building and deliveries. Sprites are sized and placed on whole
+// pixels so they fit in with the pixel camera.
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<EffectAssets>()
+      .add_startup_system(load_effect_assets)
+      .add_system(spawn_tile_effects.in_set(GameSystemSet::PostTilePlacing).after(conveyor_tile_update_graphics))
+      .add_systems(
+        (spawn_delivery_bursts, animate_placement_pops, animate_particles)
+          .in_set(GameSystemSet::PostTilePlacing),
+      );
+  }
+}
+
+#[cfg(test)]
+mod effects_test {
+  use crate::input::{chained_tile::{ChainedTileChangeEvent, ChainedTileChangePosition, ChainedTileChangeType, ChainedTilePlaceDirection}, prelude::*};
+
+  use super::*;
+
+  fn effects_app() -> App {
+    let mut app = App::new();
+    app
+      .add_plugins(MinimalPlugins)
+      .add_plugin(ConveyorBuildPlugin::new_headless(PlayfieldSize(UVec2::new(8, 8))))
+      .init_resource::<Settings>()
+      .init_resource::<EffectAssets>()
+      .add_event::<PackageDelivered>()
+      .add_system(spawn_tile_effects.in_set(GameSystemSet::PostTilePlacing).after(conveyor_tile_update_graphics))
+      .configure_sets(GameSystemSet::configure_sets());
+    app.setup();
+    app.update();
+    app
+  }
+
+  fn put_conveyor(app: &mut App, direction: ConveyorDirection) {
+    app.world.resource_mut::<SelectedTileDirection>().direction = direction;
+    app.world.send_event(ChainedTileChangeEvent {
+      position: ChainedTileChangePosition::Single(IVec2::ONE),
+      change_type: ChainedTileChangeType::Put { tile_type: TileType::Conveyor, chain: false, direction: ChainedTilePlaceDirection::Normal },
+    });
+    app.update();
+  }
+
+  fn pop_indices(app: &mut App) -> Vec<usize> {
+    app.world.query_filtered::<&TextureAtlasSprite, With<PlacementPop>>().iter(&app.world).map(|sprite| sprite.index).collect()
+  }
+
+  #[test]
+  fn placed_tiles_pop() {
+    let mut app = effects_app();
+
+    put_conveyor(&mut app, ConveyorDirection::North);
+    app.update();
+    assert_eq!(pop_indices(&mut app), vec![ConveyorDirection::North.texture_index() as usize]);
+
+    // a replaced tile pops with its new graphics
+    put_conveyor(&mut app, ConveyorDirection::East);
+    app.update();
+    let mut indices = pop_indices(&mut app);
+    indices.sort();
+    assert_eq!(indices, vec![ConveyorDirection::North.texture_index() as usize, ConveyorDirection::East.texture_index() as usize]);
+  }
+
+  #[test]
+  fn sizes_stay_on_the_pixel_grid() {
+    assert_eq!(pixel_perfect_size(16.0), 16.0);
+    assert_eq!(pixel_perfect_size(16.0 * 1.25), 20.0);
+    assert_eq!(pixel_perfect_size(17.2), 18.0);
+    assert_eq!(pixel_center(Vec2::new(3.2, -1.9)), Vec2::new(3.5, -1.5));
+  }
+
+  #[test]
+  fn diagonal_flips_turn_sprites() {
+    // where the top right corner of a frame ends up, with the flips the autotile rules
+    // use for belts, matching how the tilemap shader flips tiles
+    let top_right = |flip: TileFlip| {
+      let (flip_x, flip_y, rotation) = sprite_orientation(&flip);
+      let flipped = Vec3::new(if flip_x { -1.0 } else { 1.0 }, if flip_y { -1.0 } else { 1.0 }, 0.0);
+      (rotation * flipped).truncate().round()
+    };
+    assert_eq!(top_right(TileFlip::default()), Vec2::new(1.0, 1.0));
+    assert_eq!(top_right(TileFlip { x: true, d: true, ..default() }), Vec2::new(1.0, -1.0));
+    assert_eq!(top_right(TileFlip { x: true, y: true, ..default() }), Vec2::new(-1.0, -1.0));
+    assert_eq!(top_right(TileFlip { y: true, d: true, ..default() }), Vec2::new(-1.0, 1.0));
+    assert_eq!(top_right(TileFlip { d: true, ..default() }), Vec2::new(-1.0, -1.0));
+  }
+}
@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use serde::Serialize;
+
+use crate::{
+  delivery::prelude::*,
+  economy::prelude::*,
+  simulation::{prelude::*, run_simulation_ticks},
+  stats::{prelude::*, PackageCounts, PackageKind},
+  tile::{overlay::BACKED_UP_UTILISATION, prelude::*, validation::validate_layout},
+  GameSystemSet,
+};
+
+// Ten minutes of simulated time.
+pub const DEFAULT_HEADLESS_TICKS: u64 = 12_000;
+// Ticks run between app updates, which clear out old events.
+const TICKS_PER_UPDATE: u64 = 100;
+
+#[derive(Debug, Default, Serialize)]
+pub struct PackageReport {
+  pub kind: Option<u32>,
+  pub produced: u32,
+  pub delivered: u32,
+  pub lost: u32,
+}
+
+impl PackageReport {
+  fn new(kind: Option<PackageKind>, counts: PackageCounts) -> PackageReport {
+    PackageReport {
+      kind: kind.map(|kind| kind.0),
+      produced: counts.produced,
+      delivered: counts.delivered,
+      lost: counts.lost,
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProblemReport {
+  pub description: &'static str,
+  pub tiles: Vec<[u32; 2]>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HeadlessReport {
+  pub ticks: u64,
+  pub deliveries: PackageReport,
+  pub packages: Vec<PackageReport>,
+  // Conveyors which were backed up during the last finished statistics window.
+  pub jams: Vec<[u32; 2]>,
+  pub problems: Vec<ProblemReport>,
+  pub orders_completed: u32,
+  pub orders_failed: u32,
+  pub score: i64,
+  // Not set for levels built from an inventory alone.
+  pub money: Option<i64>,
+}
+
+// Builds an app without a window, renderer or input which only runs the conveyor
+// simulation on the given layout.
+pub fn headless_app(layout: SavedLayout) -> App {
+  let mut app = App::new();
+  app
+    .add_plugins(MinimalPlugins)
+    .add_plugin(ConveyorBuildPlugin::new_headless(PlayfieldSize(layout.playfield_size)))
+    .add_plugin(SimulationPlugin)
+    .add_plugin(StatsPlugin)
+    .add_plugin(DeliveryPlugin::new_headless())
+    .add_plugin(EconomyPlugin)
+    .insert_resource(layout)
+    .configure_sets(GameSystemSet::configure_sets());
+  app
+}
+
+pub fn run_headless(layout: SavedLayout, ticks: u64) -> HeadlessReport {
+  let mut app = headless_app(layout);
+  app.setup();
+  // run the startup systems and let the layout settle before simulating, pausing the
+  // frame-driven clock so only the ticks asked for are run
+  app.world.resource_mut::<SimulationTime>().paused = true;
+  app.update();
+
+  // updating every so often keeps the events sent each tick from piling up over long runs
+  let mut remaining = ticks;
+  while remaining > 0 {
+    let update_ticks = remaining.min(TICKS_PER_UPDATE);
+    run_simulation_ticks(&mut app.world, update_ticks);
+    app.update();
+    remaining -= update_ticks;
+  }
+
+  report(&mut app.world)
+}
+
+fn tile_coordinates(pos: &TilePos) -> [u32; 2] {
+  [pos.x, pos.y]
+}
+
+fn report(world: &mut World) -> HeadlessReport {
+  let ticks = world.resource::<SimulationTime>().tick();
+
+  let mut tilemap = world.query_filtered::<&TileStorage, With<ConveyorTileLayer>>();
+  let conveyor_directions = world.query::<&ConveyorDirection>();
+  let world: &World = world;
+
+  let tile_storage = tilemap.single(world);
+  let directions = |entity: Entity| conveyor_directions.get_manual(world, entity).ok().copied();
+  let problems = validate_layout(tile_storage, &directions)
+    .problems
+    .into_iter()
+    .map(|problem| ProblemReport {
+      description: problem.kind.description(),
+      tiles: problem.tiles.iter().map(tile_coordinates).collect(),
+    })
+    .collect();
+
+  let stats = world.resource::<ThroughputStats>();
+  let mut kinds: Vec<_> = stats.totals().keys().copied().collect();
+  kinds.sort();
+  let packages = kinds
+    .into_iter()
+    .map(|kind| PackageReport::new(Some(kind), stats.totals()[&kind]))
+    .collect();
+  let total = stats.totals().values().copied().sum::<PackageCounts>();
+
+  let mut jams: Vec<_> = stats
+    .history()
+    .next_back()
+    .map(|window| {
+      window
+        .conveyors
+        .iter()
+        .filter(|(_, samples)| samples.utilisation() >= BACKED_UP_UTILISATION)
+        .map(|(pos, _)| tile_coordinates(pos))
+        .collect()
+    })
+    .unwrap_or_default();
+  jams.sort();
+
+  let delivery_score = world.resource::<DeliveryScore>();
+  let money = world.get_resource::<Wallet>().map(Wallet::balance);
+
+  HeadlessReport {
+    ticks,
+    orders_completed: delivery_score.completed,
+    orders_failed: delivery_score.failed,
+    score: delivery_score.score,
+    money,
+    deliveries: PackageReport::new(None, total),
+    packages,
+    jams,
+    problems,
+  }
+}
+
+#[cfg(test)]
+mod headless_test {
+  use crate::{
+    delivery::orders::FAILED_ORDER_PENALTY, input::chained_tile::TileType, tile::save::{SavedCustomer, SavedTile},
+  };
+
+  use super::*;
+
+  #[test]
+  fn reports_layout_problems() {
+    let layout = SavedLayout {
+      playfield_size: UVec2::new(4, 4),
+      tiles: vec![
+        SavedTile { x: 2, y: 3, direction: ConveyorDirection::North, tile_type: TileType::Conveyor },
+        SavedTile { x: 0, y: 0, direction: ConveyorDirection::East, tile_type: TileType::Conveyor },
+      ],
+      customers: vec![],
+      starting_money: None,
+      inventory: None,
+    };
+
+    let report = run_headless(layout, 20);
+
+    assert_eq!(report.ticks, 20);
+    assert_eq!(report.problems.len(), 1);
+    assert_eq!(report.problems[0].tiles, vec![[2, 3]]);
+    assert_eq!(report.score, 0);
+  }
+
+  #[test]
+  fn unfilled_orders_fail() {
+    let layout = SavedLayout {
+      playfield_size: UVec2::new(4, 4),
+      tiles: vec![],
+      customers: vec![SavedCustomer { x: 3, y: 0, first_order_tick: 0 }],
+      starting_money: None,
+      inventory: None,
+    };
+
+    let report = run_headless(layout, 2000);
+
+    assert_eq!(report.orders_completed, 0);
+    assert_eq!(report.orders_failed, 1);
+    assert_eq!(report.score, -FAILED_ORDER_PENALTY);
+  }
+
+  #[test]
+  fn conveyors_deliver_to_customers() {
+    let layout: SavedLayout = serde_json::from_str(r#"{
+      "playfield_size": [4, 4],
+      "tiles": [
+        { "x": 0, "y": 0, "direction": "East" },
+        { "x": 1, "y": 0, "direction": "East" },
+        { "x": 2, "y": 0, "direction": "East" }
+      ],
+      "customers": [{ "x": 3, "y": 0 }]
+    }"#).unwrap();
+
+    let report = run_headless(layout, 2000);
+
+    assert!(report.deliveries.delivered > 0);
+    assert!(report.orders_completed > 0);
+    assert_eq!(report.orders_failed, 0);
+    assert!(report.score > 0);
+  }
+
+  #[test]
+  fn inventory_levels_have_no_money() {
+    let layout = SavedLayout {
+      playfield_size: UVec2::new(4, 4),
+      tiles: vec![],
+      customers: vec![],
+      starting_money: None,
+      inventory: Some([(TileType::Conveyor, 10)].into_iter().collect()),
+    };
+
+    let report = run_headless(layout, 1);
+
+    assert_eq!(report.money, None);
+  }
+}
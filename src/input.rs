@@ -1,19 +1,24 @@
 pub mod chained_tile;
-mod egui_check;
+pub mod egui_check;
+pub mod simulation_control;
+pub mod tile_picker;
 pub mod tile_rotation;
+pub mod tile_selection;
 
 use bevy::prelude::{IntoSystemConfig, Plugin};
 
-use crate::GameSystemSet;
+use crate::{GameSystemSet, tile::{prelude::toggle_conveyor_overlay, save::save_layout}};
 
 use self::{
-  chained_tile::plugin_exports::*, egui_check::plugin_exports::*, tile_rotation::plugin_exports::*,
+  chained_tile::plugin_exports::*, egui_check::plugin_exports::*, simulation_control::plugin_exports::*,
+  tile_picker::plugin_exports::*, tile_rotation::plugin_exports::*, tile_selection::plugin_exports::*,
 };
 
 pub mod prelude {
   pub use super::egui_check::prelude::*;
   pub use super::InputPlugin;
   pub use super::tile_rotation::prelude::*;
+  pub use super::tile_selection::prelude::*;
 }
 
 #[derive(Debug, Default)]
@@ -31,6 +36,17 @@ impl Plugin for InputPlugin {
       .add_system(catch_chained_tile_input.in_set(GameSystemSet::InputCollection))
       // tile rotation
       .init_resource::<SelectedTileDirection>()
-      .add_system(change_selected_tile_direction.in_set(GameSystemSet::InputCollection));
+      .add_system(change_selected_tile_direction.in_set(GameSystemSet::InputCollection))
+      .add_system(rotate_hovered_tile.in_set(GameSystemSet::InputCollection))
+      // tile selection
+      .init_resource::<SelectedTileType>()
+      .add_system(select_tile_type.in_set(GameSystemSet::InputCollection).before(catch_chained_tile_input))
+      .add_system(pick_tile.in_set(GameSystemSet::InputCollection).before(catch_chained_tile_input))
+      // conveyor overlay
+      .add_system(toggle_conveyor_overlay.in_set(GameSystemSet::InputCollection))
+      // simulation speed
+      .add_system(control_simulation_time.in_set(GameSystemSet::InputCollection))
+      // quicksave
+      .add_system(save_layout.in_set(GameSystemSet::InputCollection));
   }
 }
@@ -3,6 +3,12 @@ use bevy_ecs_tilemap::prelude::TilemapGridSize;
 
 use crate::{camera::prelude::CursorPos, tile::prelude::ConveyorTileLayer, vec2_traits::ToVec2};
 
+use super::{egui_check::prelude::GameMouseInput, tile_selection::SelectedTileType};
+
+use crate::tile::ConveyorDirection;
+
+pub use crate::tile::TileType;
+
 pub mod prelude {
   pub use super::ChainedTileChangePosition;
   pub use super::ChainedTileChangeEvent;
@@ -14,11 +20,6 @@ pub mod plugin_exports {
   pub use super::ChainedTileResource;
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum TileType {
-  Conveyor,
-}
-
 #[derive(Debug, Clone, Copy)]
 pub enum ChainedTileChangePosition {
   Single(IVec2),
@@ -51,6 +52,10 @@ pub enum ChainedTileChangeType {
     direction: ChainedTilePlaceDirection,
   },
   Delete,
+  // Turns the tile in place, keeping everything else about it.
+  Rotate {
+    direction: ConveyorDirection,
+  },
 }
 
 impl ChainedTileChangeType {
@@ -79,17 +84,40 @@ impl ChainedTileChangeEvent {
 }
 
 #[derive(Debug, Resource, Default, Reflect)]
+#[reflect(Resource)]
 pub struct ChainedTileResource {
   mouse_state: (bool, bool),
   cursor_tile_position: IVec2,
 }
 
+impl ChainedTileResource {
+  // The tile the cursor was over during the last chained tile input.
+  pub fn last_tile(&self) -> IVec2 {
+    self.cursor_tile_position
+  }
+}
+
+// The tile under the cursor, which might lie outside of the tilemap.
+pub fn cursor_tile_position(
+  cursor_pos: &CursorPos,
+  tilemap_grid_size: &TilemapGridSize,
+  tilemap_transform: &Transform,
+) -> IVec2 {
+  // convert cursor position coordinates to tilemap units
+  let mut cursor_tile_position = cursor_pos.to_map_pos(tilemap_transform) / tilemap_grid_size.to_vec2();
+  // account for tile offset
+  cursor_tile_position += Vec2::new(0.5, 0.5); 
+  // cursor position in tile-space
+  cursor_tile_position.floor().as_ivec2()
+}
+
 pub fn catch_chained_tile_input(
   mut chained_tile_event_writer: EventWriter<ChainedTileChangeEvent>,
   mut previous_frame_data: ResMut<ChainedTileResource>,
   keyboard_input: Res<Input<KeyCode>>,
-  mouse_input: ResMut<Input<MouseButton>>,
+  mouse_input: GameMouseInput,
   cursor_pos: Res<CursorPos>,
+  selected_tile_type: Res<SelectedTileType>,
   tilemap: Query<(&TilemapGridSize, &Transform, &ConveyorTileLayer)>,
 ) {
   // get the tilemap
@@ -100,13 +128,9 @@ pub fn catch_chained_tile_input(
     );
     return;
   };
-  // convert cursor position coordinates to tilemap units
-  let mut cursor_tile_position = cursor_pos.to_map_pos(tilemap_transform) / tilemap_grid_size.to_vec2();
-  // account for tile offset
-  cursor_tile_position += Vec2::new(0.5, 0.5); 
-  // cursor position in tile-space
-  let cursor_tile_position = cursor_tile_position.floor().as_ivec2();
+  let cursor_tile_position = cursor_tile_position(&cursor_pos, tilemap_grid_size, tilemap_transform);
 
+  // clicks on the ui don't build anything underneath it
   let mouse_state = (
     mouse_input.pressed(MouseButton::Left),
     mouse_input.pressed(MouseButton::Right),
@@ -114,7 +138,7 @@ pub fn catch_chained_tile_input(
   
   if cursor_tile_position != previous_frame_data.cursor_tile_position || mouse_state != previous_frame_data.mouse_state {
     let change_type = match mouse_state {
-      (true, false) => Some(ChainedTileChangeType::put(TileType::Conveyor, previous_frame_data.mouse_state.0, ChainedTilePlaceDirection::new(keyboard_input.pressed(KeyCode::LShift)))),
+      (true, false) => Some(ChainedTileChangeType::put(selected_tile_type.tile_type, previous_frame_data.mouse_state.0, ChainedTilePlaceDirection::new(keyboard_input.pressed(KeyCode::LShift)))),
       (false, true) => Some(ChainedTileChangeType::Delete),
       _ => None,
     };
@@ -1,7 +1,8 @@
-use bevy::{prelude::{Query, ResMut, Resource, Res}, reflect::Reflect};
+use bevy::{ecs::{reflect::ReflectResource, system::SystemParam}, prelude::{Input, MouseButton, Query, ResMut, Resource, Res}, reflect::Reflect};
 use bevy_egui::EguiContextQuery;
 
 pub mod prelude {
+  pub use super::GameMouseInput;
   pub use super::mouse_captured;
   pub use super::keyboard_captured;
 }
@@ -14,6 +15,7 @@ pub mod plugin_exports {
 }
 
 #[derive(Debug, Resource, Default, Reflect)]
+#[reflect(Resource)]
 pub struct EguiCapturedResources {
   mouse: bool,
   keyboard: bool,
@@ -28,6 +30,23 @@ impl EguiCapturedResources {
   }
 }
 
+// Mouse buttons as seen by the game, which ignores presses while the pointer is over the ui.
+#[derive(SystemParam)]
+pub struct GameMouseInput<'w> {
+  mouse_input: Res<'w, Input<MouseButton>>,
+  captured_resources: Res<'w, EguiCapturedResources>,
+}
+
+impl<'w> GameMouseInput<'w> {
+  pub fn pressed(&self, button: MouseButton) -> bool {
+    !self.captured_resources.mouse && self.mouse_input.pressed(button)
+  }
+
+  pub fn just_pressed(&self, button: MouseButton) -> bool {
+    !self.captured_resources.mouse && self.mouse_input.just_pressed(button)
+  }
+}
+
 pub fn check_egui_captured_resources(
   mut egui: Query<EguiContextQuery>,
   mut captured_resources: ResMut<EguiCapturedResources>,
@@ -49,5 +68,5 @@ pub fn mouse_captured(captured_resources: Res<EguiCapturedResources>) -> bool {
 }
 
 pub fn keyboard_captured(captured_resources: Res<EguiCapturedResources>) -> bool {
-  captured_resources.mouse
+  captured_resources.keyboard
 }
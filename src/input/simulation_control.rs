@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+
+use crate::simulation::prelude::*;
+
+pub mod plugin_exports {
+  pub use super::control_simulation_time;
+}
+
+pub fn control_simulation_time(
+  keyboard_input: Res<Input<KeyCode>>,
+  simulation_time: Option<ResMut<SimulationTime>>,
+) {
+  let Some(mut simulation_time) = simulation_time else { return; };
+
+  if keyboard_input.just_pressed(KeyCode::Space) {
+    simulation_time.paused = !simulation_time.paused;
+  }
+
+  if keyboard_input.just_pressed(KeyCode::Period) {
+    simulation_time.request_step();
+  }
+
+  if keyboard_input.just_pressed(KeyCode::Equals) {
+    simulation_time.speed = simulation_time.speed.faster();
+  }
+
+  if keyboard_input.just_pressed(KeyCode::Minus) {
+    simulation_time.speed = simulation_time.speed.slower();
+  }
+}
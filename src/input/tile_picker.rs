@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{camera::prelude::CursorPos, tile::prelude::*, vec2_traits::TilePosFromSigned};
+
+use super::{
+  chained_tile::cursor_tile_position, egui_check::prelude::GameMouseInput, tile_rotation::SelectedTileDirection,
+  tile_selection::SelectedTileType,
+};
+
+pub mod plugin_exports {
+  pub use super::pick_tile;
+}
+
+// Selects the type and direction of the tile under the cursor, so building from there on
+// continues it as it is.
+pub fn pick_tile(
+  keyboard_input: Res<Input<KeyCode>>,
+  mouse_input: GameMouseInput,
+  cursor_pos: Res<CursorPos>,
+  tilemap: Query<(&TileStorage, &TilemapSize, &TilemapGridSize, &Transform), With<ConveyorTileLayer>>,
+  tiles: Query<(&TileType, &ConveyorDirection)>,
+  mut selected_tile_type: ResMut<SelectedTileType>,
+  mut selected_tile_direction: ResMut<SelectedTileDirection>,
+) {
+  if !keyboard_input.just_pressed(KeyCode::Q) && !mouse_input.just_pressed(MouseButton::Middle) {
+    return;
+  }
+  let Ok((tile_storage, tilemap_size, tilemap_grid_size, tilemap_transform)) = tilemap.get_single() else { return; };
+
+  let position = cursor_tile_position(&cursor_pos, tilemap_grid_size, tilemap_transform);
+  let Ok(position) = position.to_tile_pos(tilemap_size) else { return; };
+  let Some((&tile_type, &direction)) = tile_storage.get(&position).and_then(|entity| tiles.get(entity).ok()) else { return; };
+
+  selected_tile_type.tile_type = tile_type;
+  selected_tile_direction.direction = direction;
+}
@@ -1,4 +1,9 @@
 use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{camera::prelude::CursorPos, tile::prelude::ConveyorTileLayer, vec2_traits::TilePosFromSigned};
+
+use super::chained_tile::{cursor_tile_position, ChainedTileChangeEvent, ChainedTileChangePosition, ChainedTileChangeType};
 
 pub use crate::tile::ConveyorDirection;
 
@@ -9,9 +14,11 @@ pub mod prelude {
 pub mod plugin_exports {
   pub use super::SelectedTileDirection;
   pub use super::change_selected_tile_direction;
+  pub use super::rotate_hovered_tile;
 }
 
 #[derive(Debug, Resource, Default, Reflect)]
+#[reflect(Resource)]
 pub struct SelectedTileDirection {
   pub direction: ConveyorDirection,
 }
@@ -27,3 +34,30 @@ pub fn change_selected_tile_direction(
     }
   }
 }
+
+// Turns the tile under the cursor, clockwise with T and counterclockwise with shift + T.
+pub fn rotate_hovered_tile(
+  keyboard_input: Res<Input<KeyCode>>,
+  cursor_pos: Res<CursorPos>,
+  tilemap: Query<(&TileStorage, &TilemapSize, &TilemapGridSize, &Transform), With<ConveyorTileLayer>>,
+  conveyor_directions: Query<&ConveyorDirection>,
+  mut chained_tile_event_writer: EventWriter<ChainedTileChangeEvent>,
+) {
+  if !keyboard_input.just_pressed(KeyCode::T) {
+    return;
+  }
+  let Ok((tile_storage, tilemap_size, tilemap_grid_size, tilemap_transform)) = tilemap.get_single() else { return; };
+
+  let position = cursor_tile_position(&cursor_pos, tilemap_grid_size, tilemap_transform);
+  let Ok(tile_pos) = position.to_tile_pos(tilemap_size) else { return; };
+  let Some(&direction) = tile_storage.get(&tile_pos).and_then(|entity| conveyor_directions.get(entity).ok()) else { return; };
+
+  let direction = match keyboard_input.pressed(KeyCode::LShift) {
+    true => direction.rotate_counterclockwise(),
+    false => direction.rotate_clockwise(),
+  };
+  chained_tile_event_writer.send(ChainedTileChangeEvent {
+    position: ChainedTileChangePosition::Single(position),
+    change_type: ChainedTileChangeType::Rotate { direction },
+  });
+}
@@ -0,0 +1,41 @@
+use bevy::prelude::*;
+
+use crate::tile::TileType;
+
+pub mod prelude {
+  pub use super::SelectedTileType;
+}
+
+pub mod plugin_exports {
+  pub use super::select_tile_type;
+  pub use super::SelectedTileType;
+}
+
+const HOTBAR_KEYS: [KeyCode; 9] = [
+  KeyCode::Key1,
+  KeyCode::Key2,
+  KeyCode::Key3,
+  KeyCode::Key4,
+  KeyCode::Key5,
+  KeyCode::Key6,
+  KeyCode::Key7,
+  KeyCode::Key8,
+  KeyCode::Key9,
+];
+
+#[derive(Debug, Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct SelectedTileType {
+  pub tile_type: TileType,
+}
+
+// Number keys pick the tile in the same slot of the build menu.
+pub fn select_tile_type(
+  keyboard_input: Res<Input<KeyCode>>,
+  mut selected_tile_type: ResMut<SelectedTileType>,
+) {
+  let slot = HOTBAR_KEYS.iter().position(|key| keyboard_input.just_pressed(*key));
+  if let Some(&tile_type) = slot.and_then(|slot| TileType::VALUES.get(slot)) {
+    selected_tile_type.tile_type = tile_type;
+  }
+}
@@ -0,0 +1,221 @@
+use std::{fmt::Display, path::{Path, PathBuf}};
+
+use bevy::{asset::FileAssetIo, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{settings::prelude::*, GameSystemSet};
+
+pub mod prelude {
+  pub use super::Language;
+  pub use super::Localisation;
+  pub use super::LocalisationPlugin;
+  pub use super::LocalisedName;
+}
+
+// Below the assets folder, holding one `<code>.json` string table per language.
+const LANGUAGE_DIRECTORY: &str = "lang";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect, Serialize, Deserialize)]
+pub enum Language {
+  #[default]
+  English,
+  German,
+}
+
+impl Language {
+  pub const VALUES: [Language; 2] = [Language::English, Language::German];
+
+  pub fn code(&self) -> &'static str {
+    match self {
+      Language::English => "en",
+      Language::German => "de",
+    }
+  }
+
+  // Written in the language itself, so players can find theirs whatever is selected.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Language::English => "English",
+      Language::German => "Deutsch",
+    }
+  }
+
+  pub fn path(&self) -> PathBuf {
+    FileAssetIo::get_base_path()
+      .join("assets")
+      .join(LANGUAGE_DIRECTORY)
+      .join(format!("{}.json", self.code()))
+  }
+}
+
+#[derive(Debug)]
+pub enum LocalisationError {
+  Io(std::io::Error),
+  Format(serde_json::Error),
+}
+
+impl std::fmt::Display for LocalisationError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      LocalisationError::Io(error) => write!(f, "{}", error),
+      LocalisationError::Format(error) => write!(f, "invalid string table: {}", error),
+    }
+  }
+}
+
+// Text shown to the player by key, for a single language.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct StringTable {
+  strings: HashMap<String, String>,
+}
+
+impl StringTable {
+  pub fn parse(json: &str) -> Result<StringTable, LocalisationError> {
+    serde_json::from_str(json).map_err(LocalisationError::Format)
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> Result<StringTable, LocalisationError> {
+    StringTable::parse(&std::fs::read_to_string(path).map_err(LocalisationError::Io)?)
+  }
+
+  pub fn get(&self, key: &str) -> Option<&str> {
+    self.strings.get(key).map(String::as_str)
+  }
+
+  pub fn keys(&self) -> impl Iterator<Item = &str> {
+    self.strings.keys().map(String::as_str)
+  }
+}
+
+// Values which are shown to the player by name.
+pub trait LocalisedName {
+  fn name_key(&self) -> &'static str;
+}
+
+// The string tables of the selected language and of English, which fills in for any
+// text that hasn't been translated yet.
+#[derive(Debug, Resource, Default)]
+pub struct Localisation {
+  language: Language,
+  strings: StringTable,
+  english: StringTable,
+}
+
+impl Localisation {
+  pub fn new(language: Language, strings: StringTable, english: StringTable) -> Localisation {
+    Localisation { language, strings, english }
+  }
+
+  // Missing or broken tables are logged and left empty, so the game still starts.
+  // The tables are read directly rather than through the AssetServer so the resource is
+  // ready when the plugin is built, and a language switch takes effect on the same frame.
+  pub fn load(language: Language) -> Localisation {
+    let load_table = |language: Language| {
+      let path = language.path();
+      StringTable::load(&path).unwrap_or_else(|error| {
+        error!("Could not load the {} string table {}: {}", language.name(), path.display(), error);
+        StringTable::default()
+      })
+    };
+    let english = load_table(Language::English);
+    let strings = match language {
+      Language::English => english.clone(),
+      language => load_table(language),
+    };
+    Localisation::new(language, strings, english)
+  }
+
+  pub fn language(&self) -> Language {
+    self.language
+  }
+
+  // Text for `key`, falling back to English and then to the key itself, so missing
+  // text is easy to spot in game.
+  pub fn text<'a>(&'a self, key: &'a str) -> &'a str {
+    self.strings.get(key).or_else(|| self.english.get(key)).unwrap_or(key)
+  }
+
+  // Text for `key` with each `{name}` placeholder replaced by its argument.
+  pub fn format(&self, key: &str, arguments: &[(&str, &dyn Display)]) -> String {
+    arguments.iter().fold(self.text(key).to_string(), |text, (name, value)| {
+      text.replace(&format!("{{{}}}", name), &value.to_string())
+    })
+  }
+
+  pub fn name(&self, value: &impl LocalisedName) -> &str {
+    self.text(value.name_key())
+  }
+}
+
+pub fn update_language(
+  settings: Res<Settings>,
+  mut localisation: ResMut<Localisation>,
+) {
+  if settings.language != localisation.language {
+    *localisation = Localisation::load(settings.language);
+  }
+}
+
+// Needs to be added after the settings, which pick the language.
+pub struct LocalisationPlugin;
+
+impl Plugin for LocalisationPlugin {
+  fn build(&self, app: &mut App) {
+    let language = app.world.get_resource::<Settings>().map_or(Language::English, |settings| settings.language);
+    app
+      .insert_resource(Localisation::load(language))
+      .add_system(update_language.in_set(GameSystemSet::PreInputCollection));
+  }
+}
+
+#[cfg(test)]
+mod localisation_test {
+  use super::*;
+
+  #[test]
+  fn falls_back_to_english() {
+    let english = StringTable::parse(r#"{ "greeting": "Hello", "farewell": "Goodbye" }"#).unwrap();
+    let german = StringTable::parse(r#"{ "greeting": "Hallo" }"#).unwrap();
+    let localisation = Localisation::new(Language::German, german, english);
+
+    assert_eq!(localisation.text("greeting"), "Hallo");
+    assert_eq!(localisation.text("farewell"), "Goodbye");
+    assert_eq!(localisation.text("missing"), "missing");
+  }
+
+  #[test]
+  fn placeholders() {
+    let english = StringTable::parse(r#"{ "order": "Order of {quantity} {kind}" }"#).unwrap();
+    let localisation = Localisation::new(Language::English, english.clone(), english);
+    assert_eq!(localisation.format("order", &[("quantity", &3), ("kind", &"boxes")]), "Order of 3 boxes");
+  }
+
+  #[test]
+  fn names_have_english_text() {
+    use crate::tile::{prelude::*, validation::LayoutProblemKind};
+
+    let english = StringTable::load(Language::English.path()).unwrap();
+    let directions = [ConveyorDirection::North, ConveyorDirection::East, ConveyorDirection::South, ConveyorDirection::West];
+    let keys = directions.iter().map(LocalisedName::name_key)
+      .chain(TileType::VALUES.iter().map(LocalisedName::name_key))
+      .chain(TileCategory::VALUES.iter().map(LocalisedName::name_key))
+      .chain(LayoutProblemKind::VALUES.iter().map(LocalisedName::name_key))
+      .chain(WindowModeSetting::VALUES.iter().map(LocalisedName::name_key))
+      .chain(ColorPalette::VALUES.iter().map(LocalisedName::name_key));
+    for key in keys {
+      assert!(english.get(key).is_some(), "no English text for {}", key);
+    }
+  }
+
+  #[test]
+  fn translations_only_use_english_keys() {
+    let english = StringTable::load(Language::English.path()).unwrap();
+    for language in Language::VALUES {
+      let strings = StringTable::load(language.path()).unwrap();
+      for key in strings.keys() {
+        assert!(english.get(key).is_some(), "{} has key {} which English doesn't", language.name(), key);
+      }
+    }
+  }
+}
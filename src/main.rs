@@ -1,24 +1,53 @@
 #![allow(dead_code)]
 
+mod aseprite;
 mod camera;
+mod cli;
+#[cfg(feature = "debug")]
+mod debug;
+mod delivery;
+mod economy;
+mod effects;
+mod headless;
 mod helpers;
 mod input;
+mod localisation;
+mod settings;
+mod sound;
+mod simulation;
+mod stats;
 mod tile;
 mod ui;
 mod vec2_traits;
 
+use aseprite::prelude::*;
 use bevy_egui::EguiPlugin;
 use tile::prelude::*;
 
-use bevy::{ecs::schedule::SystemSetConfig, prelude::*};
+use std::process::ExitCode;
+
+use bevy::{ecs::schedule::SystemSetConfig, prelude::*, window::WindowMode};
 use bevy_ecs_tilemap::prelude::*;
 use bevy_pixel_camera::{PixelCameraBundle, PixelCameraPlugin};
 use camera::prelude::*;
+use clap::Parser;
+use cli::{Cli, Command};
+use delivery::prelude::*;
+use economy::prelude::*;
+use effects::prelude::*;
 use input::prelude::*;
+use localisation::prelude::*;
+use settings::prelude::*;
+use sound::prelude::*;
+use simulation::prelude::*;
+use stats::prelude::*;
 use ui::prelude::*;
 
-fn startup(mut commands: Commands) {
-  commands.spawn(PixelCameraBundle::from_zoom(4));
+#[derive(Debug, Resource)]
+pub struct InitialZoom(pub i32);
+
+fn startup(mut commands: Commands, zoom: Res<InitialZoom>) {
+  commands.spawn(PixelCameraBundle::from_zoom(zoom.0));
 }
 
 pub trait OptionalResource<T> {
@@ -43,21 +72,124 @@ impl GameSystemSet {
   }
 }
 
-fn main() {
-  App::new()
-    .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
+fn load_layout(path: &std::path::Path) -> Result<SavedLayout, ExitCode> {
+  SavedLayout::load(path).map_err(|error| {
+    eprintln!("Could not load {}: {}", path.display(), error);
+    ExitCode::from(2)
+  })
+}
+
+fn validate(layout: SavedLayout) -> ExitCode {
+  let report = layout.validate();
+  let outside: Vec<_> = layout.tiles_outside_playfield().collect();
+
+  for tile in outside.iter() {
+    println!("Tile at ({}, {}) is outside of the playfield", tile.x, tile.y);
+  }
+  for problem in report.problems.iter() {
+    let tiles: Vec<_> = problem.tiles.iter().map(|pos| format!("({}, {})", pos.x, pos.y)).collect();
+    println!("{}: {}", problem.kind.description(), tiles.join(", "));
+  }
+
+  match report.is_valid() && outside.is_empty() {
+    true => {
+      println!("No problems found in {} conveyor groups", report.components.len());
+      ExitCode::SUCCESS
+    }
+    false => ExitCode::FAILURE,
+  }
+}
+
+fn headless(layout: SavedLayout, ticks: u64) -> ExitCode {
+  let report = headless::run_headless(layout, ticks);
+  match serde_json::to_string_pretty(&report) {
+    Ok(json) => {
+      println!("{}", json);
+      ExitCode::SUCCESS
+    }
+    Err(error) => {
+      eprintln!("Could not serialize the report: {}", error);
+      ExitCode::FAILURE
+    }
+  }
+}
+
+fn main() -> ExitCode {
+  let cli = Cli::parse();
+
+  match cli.command {
+    Some(Command::Headless { layout, ticks }) => {
+      return load_layout(&layout).map_or_else(|code| code, |layout| headless(layout, ticks));
+    }
+    Some(Command::Validate { layout }) => {
+      return load_layout(&layout).map_or_else(|code| code, validate);
+    }
+    None => {}
+  }
+
+  let layout = match cli.load.as_deref().map(load_layout).transpose() {
+    Ok(layout) => layout,
+    Err(code) => return code,
+  };
+  let playfield_size = cli
+    .playfield
+    .map(|size| size.0)
+    .or(layout.as_ref().map(|layout| layout.playfield_size))
+    .unwrap_or(cli::DEFAULT_PLAYFIELD_SIZE.0);
+
+  // command line options only apply to this run, they don't change the saved settings
+  let settings = Settings::load_or_default();
+  let window_size = cli.window_size.map_or(settings.resolution, |size| size.0);
+  let mut window = Window {
+    title: "Ludum Dare 53".to_string(),
+    mode: settings.window_mode.window_mode(),
+    resolution: (window_size.x as f32, window_size.y as f32).into(),
+    ..default()
+  };
+  if cli.fullscreen {
+    window.mode = WindowMode::BorderlessFullscreen;
+  }
+  let zoom = cli.zoom.unwrap_or(settings.zoom);
+
+  let mut app = App::new();
+  app
+    .add_plugins(
+      DefaultPlugins
+        .set(ImagePlugin::default_nearest())
+        .set(WindowPlugin { primary_window: Some(window), ..default() }),
+    )
+    .add_plugin(AsepritePlugin)
     .add_plugin(TilemapPlugin)
     .add_plugin(PixelCameraPlugin)
     .add_plugin(EguiPlugin)
     .add_plugin(InputPlugin)
-    .add_plugin(ConveyorBuildPlugin::new(PlayfieldSize(UVec2::new(32, 32))))
+    .add_plugin(ConveyorBuildPlugin::new(PlayfieldSize(playfield_size)))
+    .add_plugin(SimulationPlugin)
+    .add_plugin(StatsPlugin)
+    .add_plugin(DeliveryPlugin::new())
+    .add_plugin(EconomyPlugin)
     .add_plugin(UiPlugin)
+    .insert_resource(settings)
+    .add_plugin(SettingsPlugin)
+    .add_plugin(LocalisationPlugin)
+    .add_plugin(SoundPlugin)
+    .add_plugin(EffectsPlugin)
     .insert_resource(ClearColor(Color::hex("151D28").unwrap()))
+    .insert_resource(InitialZoom(zoom))
     .init_resource::<CursorPos>()
     .add_event::<CameraMoved>()
     .add_startup_system(startup)
     .add_system(movement)
     .add_system(update_cursor_pos.in_set(GameSystemSet::InputCollection))
-    .configure_sets(GameSystemSet::configure_sets())
-    .run();
+    .configure_sets(GameSystemSet::configure_sets());
+
+  if let Some(layout) = layout {
+    app.insert_resource(layout);
+  }
+
+  #[cfg(feature = "debug")]
+  app.add_plugin(debug::prelude::DebugPlugin);
+
+  app.run();
+  ExitCode::SUCCESS
 }
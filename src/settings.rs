@@ -0,0 +1,349 @@
+use std::{ops::RangeInclusive, path::{Path, PathBuf}, time::Duration};
+
+use bevy::{prelude::*, window::{PrimaryWindow, WindowMode}};
+use bevy_egui::EguiSettings;
+use bevy_pixel_camera::PixelProjection;
+use serde::{Deserialize, Serialize};
+
+use crate::localisation::prelude::*;
+
+pub mod prelude {
+  pub use super::ColorPalette;
+  pub use super::Settings;
+  pub use super::SettingsPlugin;
+  pub use super::WindowModeSetting;
+}
+
+const SETTINGS_DIRECTORY: &str = "ludum-dare-53";
+const SETTINGS_FILE: &str = "settings.json";
+
+pub const DEFAULT_RESOLUTION: UVec2 = UVec2::new(1280, 720);
+pub const DEFAULT_PAN_SPEED: f32 = 500.0;
+// Bounds of the settings the menu offers, which loaded settings are held to as well.
+pub const UI_SCALE_RANGE: RangeInclusive<f64> = 0.5..=3.0;
+pub const ZOOM_RANGE: RangeInclusive<i32> = 1..=10;
+pub const PAN_SPEED_RANGE: RangeInclusive<f32> = 100.0..=2000.0;
+pub const VOLUME_RANGE: RangeInclusive<f32> = 0.0..=1.0;
+pub const MIN_RESOLUTION: UVec2 = UVec2::new(640, 360);
+// Time the settings have to stay unchanged before they're saved, so dragging a slider
+// doesn't write the file every frame.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect, Serialize, Deserialize)]
+pub enum WindowModeSetting {
+  #[default]
+  Windowed,
+  BorderlessFullscreen,
+}
+
+impl WindowModeSetting {
+  pub const VALUES: [WindowModeSetting; 2] = [WindowModeSetting::Windowed, WindowModeSetting::BorderlessFullscreen];
+
+  pub fn window_mode(&self) -> WindowMode {
+    match self {
+      WindowModeSetting::Windowed => WindowMode::Windowed,
+      WindowModeSetting::BorderlessFullscreen => WindowMode::BorderlessFullscreen,
+    }
+  }
+}
+
+impl LocalisedName for WindowModeSetting {
+  fn name_key(&self) -> &'static str {
+    match self {
+      WindowModeSetting::Windowed => "window_mode.windowed",
+      WindowModeSetting::BorderlessFullscreen => "window_mode.borderless_fullscreen",
+    }
+  }
+}
+
+// Colours of overlays and highlights, with variants that stay apart for colour-blind players.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect, Serialize, Deserialize)]
+pub enum ColorPalette {
+  #[default]
+  Standard,
+  // Deuteranopia and protanopia
+  RedGreen,
+  // Tritanopia
+  BlueYellow,
+  // Saturated overlays over a darkened floor.
+  HighContrast,
+}
+
+impl ColorPalette {
+  pub const VALUES: [ColorPalette; 4] = [
+    ColorPalette::Standard,
+    ColorPalette::RedGreen,
+    ColorPalette::BlueYellow,
+    ColorPalette::HighContrast,
+  ];
+}
+
+impl LocalisedName for ColorPalette {
+  fn name_key(&self) -> &'static str {
+    match self {
+      ColorPalette::Standard => "palette.standard",
+      ColorPalette::RedGreen => "palette.red_green",
+      ColorPalette::BlueYellow => "palette.blue_yellow",
+      ColorPalette::HighContrast => "palette.high_contrast",
+    }
+  }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+  NoConfigDirectory,
+  Io(std::io::Error),
+  Format(serde_json::Error),
+}
+
+impl std::fmt::Display for SettingsError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SettingsError::NoConfigDirectory => write!(f, "this platform has no config directory"),
+      SettingsError::Io(error) => write!(f, "{}", error),
+      SettingsError::Format(error) => write!(f, "invalid settings file: {}", error),
+    }
+  }
+}
+
+// The player's options, kept in `settings.json` in the platform's config directory.
+// Fields missing from the file keep their defaults, so older files still load.
+#[derive(Debug, Clone, PartialEq, Resource, Reflect, Serialize, Deserialize)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct Settings {
+  pub language: Language,
+  pub window_mode: WindowModeSetting,
+  // Size of the window while it isn't fullscreen.
+  pub resolution: UVec2,
+  // Camera zoom when the game starts.
+  pub zoom: i32,
+  // Camera movement in pixels per second.
+  pub pan_speed: f32,
+  pub ui_scale: f64,
+  pub palette: ColorPalette,
+  // Arrows over every conveyor showing which way it runs.
+  pub direction_arrows: bool,
+  // Pops and particles when building and delivering.
+  pub effects: bool,
+  pub master_volume: f32,
+  pub music_volume: f32,
+  pub effects_volume: f32,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Settings {
+      language: Language::English,
+      window_mode: WindowModeSetting::Windowed,
+      resolution: DEFAULT_RESOLUTION,
+      zoom: crate::cli::DEFAULT_ZOOM,
+      pan_speed: DEFAULT_PAN_SPEED,
+      ui_scale: 1.0,
+      palette: ColorPalette::Standard,
+      direction_arrows: false,
+      effects: true,
+      master_volume: 1.0,
+      music_volume: 0.5,
+      effects_volume: 1.0,
+    }
+  }
+}
+
+impl Settings {
+  pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|directory| directory.join(SETTINGS_DIRECTORY).join(SETTINGS_FILE))
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> Result<Settings, SettingsError> {
+    let contents = std::fs::read_to_string(path).map_err(SettingsError::Io)?;
+    serde_json::from_str(&contents).map(Settings::clamped).map_err(SettingsError::Format)
+  }
+
+  // Brings values edited by hand, or saved by other versions, back into the ranges the
+  // menu allows.
+  pub fn clamped(self) -> Settings {
+    let clamp = |value: f32, range: RangeInclusive<f32>| value.clamp(*range.start(), *range.end());
+    Settings {
+      resolution: self.resolution.max(MIN_RESOLUTION),
+      zoom: self.zoom.clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end()),
+      pan_speed: clamp(self.pan_speed, PAN_SPEED_RANGE),
+      ui_scale: self.ui_scale.clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end()),
+      master_volume: clamp(self.master_volume, VOLUME_RANGE),
+      music_volume: clamp(self.music_volume, VOLUME_RANGE),
+      effects_volume: clamp(self.effects_volume, VOLUME_RANGE),
+      ..self
+    }
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SettingsError> {
+    let path = path.as_ref();
+    if let Some(directory) = path.parent() {
+      std::fs::create_dir_all(directory).map_err(SettingsError::Io)?;
+    }
+    let contents = serde_json::to_string_pretty(self).map_err(SettingsError::Format)?;
+    std::fs::write(path, contents).map_err(SettingsError::Io)
+  }
+
+  // The saved settings, or the defaults when there are none yet or they can't be read.
+  pub fn load_or_default() -> Settings {
+    let Some(path) = Settings::path() else { return Settings::default(); };
+    match Settings::load(&path) {
+      Ok(settings) => settings,
+      Err(SettingsError::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => Settings::default(),
+      Err(error) => {
+        warn!("Could not load settings from {}: {}", path.display(), error);
+        Settings::default()
+      }
+    }
+  }
+
+  // Effective volumes, scaled by the master volume.
+  pub fn music_volume(&self) -> f32 {
+    self.master_volume * self.music_volume
+  }
+
+  pub fn effects_volume(&self) -> f32 {
+    self.master_volume * self.effects_volume
+  }
+}
+
+// Changes are only applied after startup, where the window and camera are set up from
+// the settings and any command line overrides.
+fn settings_edited(settings: Res<Settings>) -> bool {
+  settings.is_changed() && !settings.is_added()
+}
+
+// Counts down to saving the settings after they were last changed.
+#[derive(Debug, Resource, Default)]
+pub struct PendingSettingsSave {
+  timer: Option<Timer>,
+}
+
+impl PendingSettingsSave {
+  // Starts the countdown again, putting off a save which is already waiting.
+  pub fn schedule(&mut self) {
+    self.timer = Some(Timer::new(SAVE_DELAY, TimerMode::Once));
+  }
+
+  // Whether the settings are due to be saved after `delta` more time passed.
+  pub fn tick(&mut self, delta: Duration) -> bool {
+    let Some(timer) = self.timer.as_mut() else { return false; };
+    if !timer.tick(delta).finished() {
+      return false;
+    }
+    self.timer = None;
+    true
+  }
+}
+
+pub fn schedule_settings_save(mut pending_save: ResMut<PendingSettingsSave>) {
+  pending_save.schedule();
+}
+
+pub fn save_settings(
+  time: Res<Time>,
+  settings: Res<Settings>,
+  mut pending_save: ResMut<PendingSettingsSave>,
+) {
+  if !pending_save.tick(time.delta()) {
+    return;
+  }
+  let Some(path) = Settings::path() else {
+    warn!("Could not save settings: {}", SettingsError::NoConfigDirectory);
+    return;
+  };
+  if let Err(error) = settings.save(&path) {
+    error!("Could not save settings to {}: {}", path.display(), error);
+  }
+}
+
+pub fn apply_window_settings(
+  settings: Res<Settings>,
+  mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+  let Ok(mut window) = windows.get_single_mut() else { return; };
+  let mode = settings.window_mode.window_mode();
+  if window.mode != mode {
+    window.mode = mode;
+  }
+  let resolution = settings.resolution.as_vec2();
+  if mode == WindowMode::Windowed && Vec2::new(window.resolution.width(), window.resolution.height()) != resolution {
+    window.resolution.set(resolution.x, resolution.y);
+  }
+}
+
+pub fn apply_ui_scale(
+  settings: Res<Settings>,
+  mut egui_settings: ResMut<EguiSettings>,
+) {
+  egui_settings.scale_factor = settings.ui_scale;
+}
+
+pub fn apply_zoom(
+  settings: Res<Settings>,
+  mut cameras: Query<&mut PixelProjection, With<Camera>>,
+) {
+  for mut projection in cameras.iter_mut() {
+    projection.zoom = settings.zoom;
+  }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+  fn build(&self, app: &mut App) {
+    if !app.world.contains_resource::<Settings>() {
+      app.insert_resource(Settings::load_or_default());
+    }
+    app
+      .init_resource::<PendingSettingsSave>()
+      .add_startup_system(apply_ui_scale)
+      .add_systems((apply_window_settings, apply_ui_scale, apply_zoom, schedule_settings_save).distributive_run_if(settings_edited))
+      .add_system(save_settings.after(schedule_settings_save));
+  }
+}
+
+#[cfg(test)]
+mod settings_test {
+  use super::*;
+
+  #[test]
+  fn round_trip() {
+    let settings = Settings { palette: ColorPalette::RedGreen, music_volume: 0.25, ..default() };
+    let json = serde_json::to_string(&settings).unwrap();
+    assert_eq!(serde_json::from_str::<Settings>(&json).unwrap(), settings);
+  }
+
+  #[test]
+  fn loaded_settings_are_clamped() {
+    let settings: Settings = serde_json::from_str(r#"{ "zoom": 0, "ui_scale": 12.0, "pan_speed": -5.0, "resolution": [20, 2000], "music_volume": 3.0 }"#).unwrap();
+    let settings = settings.clamped();
+    assert_eq!(settings.zoom, *ZOOM_RANGE.start());
+    assert_eq!(settings.ui_scale, *UI_SCALE_RANGE.end());
+    assert_eq!(settings.pan_speed, *PAN_SPEED_RANGE.start());
+    assert_eq!(settings.resolution, UVec2::new(MIN_RESOLUTION.x, 2000));
+    assert_eq!(settings.music_volume, 1.0);
+    assert_eq!(Settings::default().clamped(), Settings::default());
+  }
+
+  #[test]
+  fn saves_wait_for_edits_to_stop() {
+    let mut pending_save = PendingSettingsSave::default();
+    assert!(!pending_save.tick(SAVE_DELAY));
+
+    pending_save.schedule();
+    assert!(!pending_save.tick(SAVE_DELAY / 2));
+    // another edit puts the save off again
+    pending_save.schedule();
+    assert!(!pending_save.tick(SAVE_DELAY / 2));
+    assert!(pending_save.tick(SAVE_DELAY / 2));
+    assert!(!pending_save.tick(SAVE_DELAY));
+  }
+
+  #[test]
+  fn missing_fields_keep_defaults() {
+    let settings: Settings = serde_json::from_str(r#"{ "zoom": 2 }"#).unwrap();
+    assert_eq!(settings, Settings { zoom: 2, ..default() });
+  }
+}
@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*};
+
+use crate::GameSystemSet;
+
+pub mod prelude {
+  pub use super::SimulationPlugin;
+  pub use super::SimulationSchedule;
+  pub use super::SimulationSpeed;
+  pub use super::SimulationSystemSet;
+  pub use super::SimulationTime;
+}
+
+// Length of a single simulation tick. Simulation systems should use this instead of
+// `Time`, so the results don't depend on the frame rate or the speed multiplier.
+pub const SIMULATION_TICK_LENGTH: Duration = Duration::from_millis(50);
+// Upper bound on the ticks run in a single frame, so a slow frame can't snowball.
+const MAX_TICKS_PER_FRAME: u32 = 32;
+
+// Systems added to this schedule run once for every simulation tick.
+#[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct SimulationSchedule;
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum SimulationSystemSet {
+  Update,
+  Statistics,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Reflect)]
+pub enum SimulationSpeed {
+  #[default]
+  Normal,
+  Double,
+  Quadruple,
+  Octuple,
+}
+
+impl SimulationSpeed {
+  pub const VALUES: [SimulationSpeed; 4] = [
+    SimulationSpeed::Normal,
+    SimulationSpeed::Double,
+    SimulationSpeed::Quadruple,
+    SimulationSpeed::Octuple,
+  ];
+
+  pub fn multiplier(&self) -> u32 {
+    match self {
+      SimulationSpeed::Normal => 1,
+      SimulationSpeed::Double => 2,
+      SimulationSpeed::Quadruple => 4,
+      SimulationSpeed::Octuple => 8,
+    }
+  }
+
+  pub fn faster(&self) -> SimulationSpeed {
+    match self {
+      SimulationSpeed::Normal => SimulationSpeed::Double,
+      SimulationSpeed::Double => SimulationSpeed::Quadruple,
+      SimulationSpeed::Quadruple | SimulationSpeed::Octuple => SimulationSpeed::Octuple,
+    }
+  }
+
+  pub fn slower(&self) -> SimulationSpeed {
+    match self {
+      SimulationSpeed::Normal | SimulationSpeed::Double => SimulationSpeed::Normal,
+      SimulationSpeed::Quadruple => SimulationSpeed::Double,
+      SimulationSpeed::Octuple => SimulationSpeed::Quadruple,
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      SimulationSpeed::Normal => "1x",
+      SimulationSpeed::Double => "2x",
+      SimulationSpeed::Quadruple => "4x",
+      SimulationSpeed::Octuple => "8x",
+    }
+  }
+}
+
+#[derive(Debug, Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct SimulationTime {
+  pub paused: bool,
+  pub speed: SimulationSpeed,
+  step_requested: bool,
+  tick: u64,
+  accumulated: Duration,
+}
+
+impl SimulationTime {
+  // Number of ticks simulated so far.
+  pub fn tick(&self) -> u64 {
+    self.tick
+  }
+
+  pub fn elapsed(&self) -> Duration {
+    SIMULATION_TICK_LENGTH * self.tick as u32
+  }
+
+  // Runs exactly one tick on the next frame, even while paused.
+  pub fn request_step(&mut self) {
+    self.step_requested = true;
+  }
+
+  // Works out how many ticks to run for a frame which took `delta` real time.
+  fn ticks_for_frame(&mut self, delta: Duration) -> u32 {
+    if std::mem::take(&mut self.step_requested) {
+      self.accumulated = Duration::ZERO;
+      return 1;
+    }
+    if self.paused {
+      return 0;
+    }
+
+    self.accumulated += delta * self.speed.multiplier();
+    let mut ticks = 0;
+    while self.accumulated >= SIMULATION_TICK_LENGTH && ticks < MAX_TICKS_PER_FRAME {
+      self.accumulated -= SIMULATION_TICK_LENGTH;
+      ticks += 1;
+    }
+    if ticks == MAX_TICKS_PER_FRAME {
+      // drop the time we couldn't catch up on rather than carrying it forever
+      self.accumulated = Duration::ZERO;
+    }
+    ticks
+  }
+}
+
+// Runs the simulation schedule `ticks` times, regardless of pause state and speed.
+pub fn run_simulation_ticks(world: &mut World, ticks: u64) {
+  for _ in 0..ticks {
+    world.run_schedule(SimulationSchedule);
+    world.resource_mut::<SimulationTime>().tick += 1;
+  }
+}
+
+// Runs the simulation schedule once per tick which is due this frame.
+pub fn run_simulation_schedule(world: &mut World) {
+  let delta = world.resource::<Time>().delta();
+  let ticks = world.resource_mut::<SimulationTime>().ticks_for_frame(delta);
+  run_simulation_ticks(world, ticks as u64);
+}
+
+#[derive(Debug, Default)]
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<SimulationTime>()
+      .init_schedule(SimulationSchedule)
+      .edit_schedule(SimulationSchedule, |schedule| {
+        schedule.configure_set(SimulationSystemSet::Update.before(SimulationSystemSet::Statistics));
+      })
+      .add_system(run_simulation_schedule.in_set(GameSystemSet::PostTilePlacing));
+  }
+}
+
+#[cfg(test)]
+mod simulation_test {
+  use super::*;
+
+  #[test]
+  fn ticks_follow_speed() {
+    let mut time = SimulationTime::default();
+    assert_eq!(time.ticks_for_frame(SIMULATION_TICK_LENGTH * 3), 3);
+
+    time.speed = SimulationSpeed::Quadruple;
+    assert_eq!(time.ticks_for_frame(SIMULATION_TICK_LENGTH / 2), 2);
+  }
+
+  #[test]
+  fn leftover_time_carries_over() {
+    let mut time = SimulationTime::default();
+    assert_eq!(time.ticks_for_frame(SIMULATION_TICK_LENGTH * 2 / 3), 0);
+    assert_eq!(time.ticks_for_frame(SIMULATION_TICK_LENGTH * 2 / 3), 1);
+  }
+
+  #[test]
+  fn pause_and_step() {
+    let mut time = SimulationTime { paused: true, ..default() };
+    assert_eq!(time.ticks_for_frame(SIMULATION_TICK_LENGTH * 10), 0);
+
+    time.request_step();
+    assert_eq!(time.ticks_for_frame(SIMULATION_TICK_LENGTH * 10), 1);
+    assert_eq!(time.ticks_for_frame(SIMULATION_TICK_LENGTH * 10), 0);
+  }
+
+  #[test]
+  fn same_ticks_regardless_of_frame_rate() {
+    let run = |frame_length: Duration, frames: u32| {
+      let mut time = SimulationTime { speed: SimulationSpeed::Octuple, ..default() };
+      (0..frames).map(|_| time.ticks_for_frame(frame_length)).sum::<u32>()
+    };
+
+    assert_eq!(run(Duration::from_millis(10), 100), run(Duration::from_millis(25), 40));
+  }
+}
@@ -0,0 +1,314 @@
+use std::{sync::Arc, time::Duration};
+
+use bevy::{
+  audio::{AddAudioSource, AudioSink, AudioSinkPlayback, Decodable, Source},
+  prelude::*,
+  reflect::TypeUuid,
+  utils::HashMap,
+};
+
+use crate::{
+  GameSystemSet,
+  delivery::orders::{OrderEvent, OrderOutcome, PackageDelivered},
+  settings::prelude::*,
+  tile::prelude::*,
+};
+
+pub mod prelude {
+  pub use super::SoundPlugin;
+}
+
+const SAMPLE_RATE: u32 = 44100;
+// The same effect doesn't play again sooner than this, so a drag which places a whole
+// line of tiles in one go sounds like a single placement.
+const EFFECT_COOLDOWN: Duration = Duration::from_millis(70);
+const MUSIC_BEATS_PER_MINUTE: f32 = 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+  Place,
+  Remove,
+  Rotate,
+  Delivered,
+  OrderFailed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Wave {
+  Square,
+  Triangle,
+  Noise,
+}
+
+// A note which slides from one pitch to another while fading out.
+#[derive(Debug, Clone, Copy)]
+struct Tone {
+  wave: Wave,
+  start_hz: f32,
+  end_hz: f32,
+  start: f32,
+  length: f32,
+  volume: f32,
+}
+
+impl Tone {
+  const fn new(wave: Wave, start_hz: f32, end_hz: f32, start: f32, length: f32, volume: f32) -> Tone {
+    Tone { wave, start_hz, end_hz, start, length, volume }
+  }
+}
+
+impl SoundEffect {
+  const VALUES: [SoundEffect; 5] = [
+    SoundEffect::Place,
+    SoundEffect::Remove,
+    SoundEffect::Rotate,
+    SoundEffect::Delivered,
+    SoundEffect::OrderFailed,
+  ];
+
+  fn tones(&self) -> Vec<Tone> {
+    match self {
+      SoundEffect::Place => vec![Tone::new(Wave::Square, 660.0, 880.0, 0.0, 0.06, 0.25)],
+      SoundEffect::Remove => vec![Tone::new(Wave::Noise, 0.0, 0.0, 0.0, 0.09, 0.3), Tone::new(Wave::Square, 220.0, 110.0, 0.0, 0.08, 0.15)],
+      SoundEffect::Rotate => vec![Tone::new(Wave::Triangle, 520.0, 640.0, 0.0, 0.05, 0.4)],
+      SoundEffect::Delivered => vec![
+        Tone::new(Wave::Triangle, 1046.5, 1046.5, 0.0, 0.08, 0.4),
+        Tone::new(Wave::Triangle, 1318.5, 1318.5, 0.07, 0.08, 0.4),
+        Tone::new(Wave::Triangle, 1568.0, 1568.0, 0.14, 0.16, 0.4),
+      ],
+      SoundEffect::OrderFailed => vec![
+        Tone::new(Wave::Square, 330.0, 311.1, 0.0, 0.18, 0.2),
+        Tone::new(Wave::Square, 261.6, 196.0, 0.18, 0.35, 0.2),
+      ],
+    }
+  }
+}
+
+// A bass line and arpeggio over four chords, looped as background music.
+fn music_tones() -> Vec<Tone> {
+  let beat = 60.0 / MUSIC_BEATS_PER_MINUTE;
+  let chords: [[f32; 3]; 4] = [
+    [261.6, 329.6, 392.0],
+    [220.0, 261.6, 329.6],
+    [174.6, 220.0, 261.6],
+    [196.0, 246.9, 293.7],
+  ];
+  let mut tones = Vec::new();
+  for (bar, chord) in chords.iter().enumerate() {
+    let bar_start = bar as f32 * 4.0 * beat;
+    tones.push(Tone::new(Wave::Triangle, chord[0] / 2.0, chord[0] / 2.0, bar_start, 4.0 * beat, 0.3));
+    for step in 0..8 {
+      let note = [0, 1, 2, 1][step % 4];
+      let hz = chord[note] * 2.0;
+      tones.push(Tone::new(Wave::Triangle, hz, hz, bar_start + step as f32 * beat / 2.0, beat / 2.0, 0.12));
+    }
+  }
+  tones
+}
+
+fn render(tones: &[Tone]) -> Arc<[f32]> {
+  let length = tones.iter().map(|tone| tone.start + tone.length).fold(0.0, f32::max);
+  let mut samples = vec![0.0; (length * SAMPLE_RATE as f32).ceil() as usize];
+  let mut noise_state: u32 = 0x2545_f491;
+
+  for tone in tones {
+    let first_sample = (tone.start * SAMPLE_RATE as f32) as usize;
+    let sample_count = (tone.length * SAMPLE_RATE as f32) as usize;
+    let mut phase = 0.0f32;
+    for index in 0..sample_count {
+      let progress = index as f32 / sample_count as f32;
+      let hz = tone.start_hz + (tone.end_hz - tone.start_hz) * progress;
+      phase = (phase + hz / SAMPLE_RATE as f32).fract();
+      let value = match tone.wave {
+        Wave::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+        Wave::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        Wave::Noise => {
+          noise_state ^= noise_state << 13;
+          noise_state ^= noise_state >> 17;
+          noise_state ^= noise_state << 5;
+          noise_state as f32 / u32::MAX as f32 * 2.0 - 1.0
+        }
+      };
+      // a few milliseconds of fade in keeps notes from clicking
+      let attack = (index as f32 / (0.004 * SAMPLE_RATE as f32)).min(1.0);
+      let decay = (1.0 - progress).powi(2);
+      if let Some(sample) = samples.get_mut(first_sample + index) {
+        *sample += value * tone.volume * attack * decay;
+      }
+    }
+  }
+  samples.into()
+}
+
+// Sounds synthesized when the game starts, so they don't need any audio files.
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "9c4e1f7a-2b6d-4e83-a5f0-3d8c7b1e6a29"]
+pub struct SynthSound {
+  samples: Arc<[f32]>,
+}
+
+pub struct SynthDecoder {
+  samples: Arc<[f32]>,
+  position: usize,
+}
+
+impl Iterator for SynthDecoder {
+  type Item = f32;
+
+  fn next(&mut self) -> Option<f32> {
+    let sample = self.samples.get(self.position).copied();
+    self.position += 1;
+    sample
+  }
+}
+
+impl Source for SynthDecoder {
+  fn current_frame_len(&self) -> Option<usize> {
+    Some(self.samples.len().saturating_sub(self.position))
+  }
+
+  fn channels(&self) -> u16 {
+    1
+  }
+
+  fn sample_rate(&self) -> u32 {
+    SAMPLE_RATE
+  }
+
+  fn total_duration(&self) -> Option<Duration> {
+    Some(Duration::from_secs_f64(self.samples.len() as f64 / SAMPLE_RATE as f64))
+  }
+}
+
+impl Decodable for SynthSound {
+  type DecoderItem = f32;
+  type Decoder = SynthDecoder;
+
+  fn decoder(&self) -> SynthDecoder {
+    SynthDecoder { samples: self.samples.clone(), position: 0 }
+  }
+}
+
+#[derive(Debug, Resource, Default)]
+pub struct Sounds {
+  effects: HashMap<SoundEffect, Handle<SynthSound>>,
+  // Elapsed time at which each effect last played.
+  last_played: HashMap<SoundEffect, Duration>,
+  music: Option<Handle<AudioSink>>,
+}
+
+impl Sounds {
+  // Whether `effect` may play at `now`, noting it as played if so.
+  fn take_cooldown(&mut self, effect: SoundEffect, now: Duration) -> bool {
+    if self.last_played.get(&effect).is_some_and(|last| now < *last + EFFECT_COOLDOWN) {
+      return false;
+    }
+    self.last_played.insert(effect, now);
+    true
+  }
+}
+
+pub fn setup_sounds(
+  mut sounds: ResMut<Sounds>,
+  mut synth_sounds: ResMut<Assets<SynthSound>>,
+  audio: Res<Audio<SynthSound>>,
+  audio_sinks: Res<Assets<AudioSink>>,
+  settings: Res<Settings>,
+) {
+  for effect in SoundEffect::VALUES {
+    let sound = synth_sounds.add(SynthSound { samples: render(&effect.tones()) });
+    sounds.effects.insert(effect, sound);
+  }
+
+  // without an audio device the sink is never created and the handle stays empty
+  let music = synth_sounds.add(SynthSound { samples: render(&music_tones()) });
+  let music_sink = audio.play_with_settings(music, PlaybackSettings::LOOP.with_volume(settings.music_volume()));
+  sounds.music = Some(audio_sinks.get_handle(music_sink));
+}
+
+pub fn play_sound_effects(
+  time: Res<Time>,
+  mut tile_edits: EventReader<TileEdited>,
+  mut deliveries: EventReader<PackageDelivered>,
+  mut order_events: EventReader<OrderEvent>,
+  mut sounds: ResMut<Sounds>,
+  audio: Res<Audio<SynthSound>>,
+  settings: Res<Settings>,
+) {
+  let mut effects: Vec<_> = tile_edits
+    .iter()
+    .map(|edited| match edited.edit {
+      TileEdit::Placed(_) => SoundEffect::Place,
+      TileEdit::Removed => SoundEffect::Remove,
+      TileEdit::Rotated => SoundEffect::Rotate,
+    })
+    .collect();
+  effects.extend(deliveries.iter().map(|_| SoundEffect::Delivered));
+  effects.extend(
+    order_events
+      .iter()
+      .filter(|order_event| matches!(order_event.outcome, OrderOutcome::Failed { .. }))
+      .map(|_| SoundEffect::OrderFailed),
+  );
+
+  let volume = settings.effects_volume();
+  if volume <= 0.0 {
+    return;
+  }
+  for effect in effects {
+    if !sounds.take_cooldown(effect, time.elapsed()) {
+      continue;
+    }
+    let Some(sound) = sounds.effects.get(&effect) else { continue; };
+    audio.play_with_settings(sound.clone(), PlaybackSettings::ONCE.with_volume(volume));
+  }
+}
+
+pub fn update_music_volume(
+  settings: Res<Settings>,
+  sounds: Res<Sounds>,
+  audio_sinks: Res<Assets<AudioSink>>,
+) {
+  if !settings.is_changed() {
+    return;
+  }
+  let Some(music) = sounds.music.as_ref().and_then(|music| audio_sinks.get(music)) else { return; };
+  music.set_volume(settings.music_volume());
+}
+
+// Needs the settings and bevy's audio plugin. Without an audio device nothing plays.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_audio_source::<SynthSound>()
+      .init_resource::<Sounds>()
+      .add_startup_system(setup_sounds)
+      .add_systems((play_sound_effects, update_music_volume).in_set(GameSystemSet::PostTilePlacing));
+  }
+}
+
+#[cfg(test)]
+mod sound_test {
+  use super::*;
+
+  #[test]
+  fn cooldown() {
+    let mut sounds = Sounds::default();
+    assert!(sounds.take_cooldown(SoundEffect::Place, Duration::ZERO));
+    assert!(!sounds.take_cooldown(SoundEffect::Place, EFFECT_COOLDOWN / 2));
+    assert!(sounds.take_cooldown(SoundEffect::Rotate, EFFECT_COOLDOWN / 2));
+    assert!(sounds.take_cooldown(SoundEffect::Place, EFFECT_COOLDOWN));
+  }
+
+  #[test]
+  fn rendered_sounds_stay_in_range() {
+    for effect in SoundEffect::VALUES {
+      let samples = render(&effect.tones());
+      assert!(!samples.is_empty());
+      assert!(samples.iter().all(|sample| sample.abs() <= 1.0));
+    }
+    assert!(render(&music_tones()).iter().all(|sample| sample.abs() <= 1.0));
+  }
+}
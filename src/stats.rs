@@ -0,0 +1,372 @@
+use std::{collections::VecDeque, fmt::Display, io::Write};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::simulation::{prelude::*, SIMULATION_TICK_LENGTH};
+
+pub mod prelude {
+  pub use super::StatsPlugin;
+  pub use super::StatsWindow;
+  pub use super::ThroughputStats;
+}
+
+// Length of a single statistics window in seconds.
+pub const DEFAULT_WINDOW_LENGTH: f32 = 5.0;
+// Amount of finished windows kept around for the graphs and the CSV export.
+pub const DEFAULT_WINDOW_HISTORY: usize = 120;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, Reflect, Default)]
+pub struct PackageKind(pub u32);
+
+// Icons of package kinds differ in shape as well as colour.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum PackageShape {
+  Circle,
+  Square,
+  Triangle,
+  Diamond,
+}
+
+impl PackageShape {
+  pub const VALUES: [PackageShape; 4] = [PackageShape::Circle, PackageShape::Square, PackageShape::Triangle, PackageShape::Diamond];
+}
+
+// Picked to stay apart with any kind of colour blindness.
+const PACKAGE_COLORS: [[u8; 3]; 4] = [
+  [0xe6, 0x9f, 0x00],
+  [0x56, 0xb4, 0xe9],
+  [0x00, 0x9e, 0x73],
+  [0xf0, 0xe4, 0x42],
+];
+
+impl PackageKind {
+  pub fn shape(&self) -> PackageShape {
+    PackageShape::VALUES[self.0 as usize % PackageShape::VALUES.len()]
+  }
+
+  pub fn color(&self) -> [u8; 3] {
+    PACKAGE_COLORS[self.0 as usize % PACKAGE_COLORS.len()]
+  }
+}
+
+impl Display for PackageKind {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "Package {}", self.0)
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PackageOutcome {
+  Produced,
+  Delivered,
+  Lost,
+}
+
+// Sent by anything which creates, delivers or destroys a package.
+#[derive(Debug, Clone)]
+pub struct PackageEvent {
+  pub kind: PackageKind,
+  pub outcome: PackageOutcome,
+}
+
+// Sent once per simulation tick for every conveyor, `occupied` being true when
+// the conveyor was carrying a package during that tick.
+#[derive(Debug, Clone)]
+pub struct ConveyorLoadSample {
+  pub pos: TilePos,
+  pub occupied: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PackageCounts {
+  pub produced: u32,
+  pub delivered: u32,
+  pub lost: u32,
+}
+
+impl PackageCounts {
+  fn record(&mut self, outcome: PackageOutcome) {
+    match outcome {
+      PackageOutcome::Produced => self.produced += 1,
+      PackageOutcome::Delivered => self.delivered += 1,
+      PackageOutcome::Lost => self.lost += 1,
+    }
+  }
+}
+
+impl std::iter::Sum for PackageCounts {
+  fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+    iter.fold(PackageCounts::default(), |mut acc, counts| {
+      acc.produced += counts.produced;
+      acc.delivered += counts.delivered;
+      acc.lost += counts.lost;
+      acc
+    })
+  }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ConveyorSamples {
+  pub occupied: u32,
+  pub total: u32,
+}
+
+impl ConveyorSamples {
+  pub fn utilisation(&self) -> f32 {
+    match self.total {
+      0 => 0.0,
+      total => self.occupied as f32 / total as f32,
+    }
+  }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct StatsWindow {
+  pub start: f32,
+  pub packages: HashMap<PackageKind, PackageCounts>,
+  pub conveyors: HashMap<TilePos, ConveyorSamples>,
+}
+
+impl StatsWindow {
+  fn new(start: f32) -> StatsWindow {
+    StatsWindow { start, ..default() }
+  }
+
+  pub fn counts(&self, kind: PackageKind) -> PackageCounts {
+    self.packages.get(&kind).copied().unwrap_or_default()
+  }
+
+  pub fn total_counts(&self) -> PackageCounts {
+    self.packages.values().copied().sum()
+  }
+
+  pub fn average_utilisation(&self) -> f32 {
+    if self.conveyors.is_empty() {
+      return 0.0;
+    }
+    self.conveyors.values().map(ConveyorSamples::utilisation).sum::<f32>() / self.conveyors.len() as f32
+  }
+}
+
+#[derive(Debug, Resource)]
+pub struct ThroughputStats {
+  pub window_length: f32,
+  pub max_history: usize,
+  elapsed: f32,
+  current: StatsWindow,
+  history: VecDeque<StatsWindow>,
+  totals: HashMap<PackageKind, PackageCounts>,
+}
+
+impl Default for ThroughputStats {
+  fn default() -> Self {
+    ThroughputStats::new(DEFAULT_WINDOW_LENGTH, DEFAULT_WINDOW_HISTORY)
+  }
+}
+
+impl ThroughputStats {
+  pub fn new(window_length: f32, max_history: usize) -> ThroughputStats {
+    ThroughputStats {
+      window_length,
+      max_history,
+      elapsed: 0.0,
+      current: StatsWindow::new(0.0),
+      history: VecDeque::new(),
+      totals: HashMap::new(),
+    }
+  }
+
+  pub fn current(&self) -> &StatsWindow {
+    &self.current
+  }
+
+  pub fn history(&self) -> impl DoubleEndedIterator<Item = &StatsWindow> + ExactSizeIterator {
+    self.history.iter()
+  }
+
+  // Counts over everything recorded so far, including windows dropped from the history.
+  pub fn totals(&self) -> &HashMap<PackageKind, PackageCounts> {
+    &self.totals
+  }
+
+  pub fn record_package(&mut self, kind: PackageKind, outcome: PackageOutcome) {
+    self.current.packages.entry(kind).or_default().record(outcome);
+    self.totals.entry(kind).or_default().record(outcome);
+  }
+
+  pub fn record_conveyor(&mut self, pos: TilePos, occupied: bool) {
+    let samples = self.current.conveyors.entry(pos).or_default();
+    samples.total += 1;
+    if occupied {
+      samples.occupied += 1;
+    }
+  }
+
+  // All package kinds which show up in either the history or the current window, sorted.
+  pub fn kinds(&self) -> Vec<PackageKind> {
+    let mut kinds: Vec<_> = self
+      .history
+      .iter()
+      .chain(std::iter::once(&self.current))
+      .flat_map(|window| window.packages.keys().copied())
+      .collect();
+    kinds.sort();
+    kinds.dedup();
+    kinds
+  }
+
+  // Advances the clock, closing the current window and starting a new one for every
+  // `window_length` seconds which passed.
+  pub fn advance(&mut self, delta_seconds: f32) {
+    self.elapsed += delta_seconds;
+    while self.elapsed - self.current.start >= self.window_length {
+      let next_start = self.current.start + self.window_length;
+      let finished = std::mem::replace(&mut self.current, StatsWindow::new(next_start));
+      self.history.push_back(finished);
+      while self.history.len() > self.max_history {
+        self.history.pop_front();
+      }
+    }
+  }
+
+  // One row per package kind and one per conveyor for every finished window, told
+  // apart by the `row` column.
+  pub fn write_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+    writeln!(writer, "window_start,row,package_kind,produced,delivered,lost,conveyor_x,conveyor_y,busy_ticks,sampled_ticks")?;
+    let kinds = self.kinds();
+    for window in self.history.iter() {
+      for kind in kinds.iter() {
+        let counts = window.counts(*kind);
+        writeln!(
+          writer,
+          "{},package,{},{},{},{},,,,",
+          window.start,
+          kind.0,
+          counts.produced,
+          counts.delivered,
+          counts.lost,
+        )?;
+      }
+      let mut conveyors: Vec<_> = window.conveyors.iter().collect();
+      conveyors.sort_by_key(|(pos, _)| (pos.y, pos.x));
+      for (pos, samples) in conveyors {
+        writeln!(writer, "{},conveyor,,,,,{},{},{},{}", window.start, pos.x, pos.y, samples.occupied, samples.total)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+pub fn record_package_events(
+  mut package_events: EventReader<PackageEvent>,
+  mut stats: ResMut<ThroughputStats>,
+) {
+  for event in package_events.iter() {
+    stats.record_package(event.kind, event.outcome);
+  }
+}
+
+pub fn record_conveyor_load_samples(
+  mut load_samples: EventReader<ConveyorLoadSample>,
+  mut stats: ResMut<ThroughputStats>,
+) {
+  for sample in load_samples.iter() {
+    stats.record_conveyor(sample.pos, sample.occupied);
+  }
+}
+
+pub fn advance_stats_window(mut stats: ResMut<ThroughputStats>) {
+  stats.advance(SIMULATION_TICK_LENGTH.as_secs_f32());
+}
+
+#[derive(Debug, Default)]
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<ThroughputStats>()
+      .add_event::<PackageEvent>()
+      .add_event::<ConveyorLoadSample>()
+      .add_systems(
+        (
+          record_package_events,
+          record_conveyor_load_samples,
+          advance_stats_window,
+        )
+          .chain()
+          .in_set(SimulationSystemSet::Statistics)
+          .in_schedule(SimulationSchedule)
+      );
+  }
+}
+
+#[cfg(test)]
+mod stats_test {
+  use super::*;
+
+  #[test]
+  fn windows_roll_over() {
+    let mut stats = ThroughputStats::new(1.0, 2);
+
+    stats.record_package(PackageKind(0), PackageOutcome::Produced);
+    stats.advance(0.5);
+    stats.record_package(PackageKind(0), PackageOutcome::Delivered);
+    stats.advance(0.6);
+    stats.record_package(PackageKind(1), PackageOutcome::Lost);
+
+    assert_eq!(stats.history().len(), 1);
+    let first = stats.history().next().unwrap();
+    assert_eq!(first.counts(PackageKind(0)), PackageCounts { produced: 1, delivered: 1, lost: 0 });
+    assert_eq!(stats.current().counts(PackageKind(1)).lost, 1);
+    assert_eq!(stats.current().start, 1.0);
+
+    stats.advance(5.0);
+    assert_eq!(stats.history().len(), 2);
+    assert_eq!(stats.current().start, 6.0);
+    assert_eq!(stats.totals()[&PackageKind(0)], PackageCounts { produced: 1, delivered: 1, lost: 0 });
+  }
+
+  #[test]
+  fn package_kinds_have_distinct_shapes() {
+    let kind_count = crate::delivery::orders::PACKAGE_KIND_COUNT;
+    let shapes: std::collections::HashSet<_> = (0..kind_count).map(|kind| PackageKind(kind).shape()).collect();
+    assert_eq!(shapes.len(), kind_count as usize);
+  }
+
+  #[test]
+  fn conveyor_utilisation() {
+    let mut stats = ThroughputStats::new(1.0, 2);
+    let pos = TilePos { x: 1, y: 2 };
+
+    stats.record_conveyor(pos, true);
+    stats.record_conveyor(pos, false);
+    stats.record_conveyor(pos, true);
+    stats.record_conveyor(pos, true);
+
+    assert_eq!(stats.current().conveyors[&pos].utilisation(), 0.75);
+    assert_eq!(stats.current().average_utilisation(), 0.75);
+  }
+
+  #[test]
+  fn csv_export() {
+    let mut stats = ThroughputStats::new(1.0, 4);
+    stats.record_package(PackageKind(3), PackageOutcome::Produced);
+    stats.record_conveyor(TilePos { x: 1, y: 0 }, true);
+    stats.record_conveyor(TilePos { x: 0, y: 0 }, true);
+    stats.record_conveyor(TilePos { x: 0, y: 0 }, false);
+    stats.advance(1.0);
+
+    let mut output = Vec::new();
+    stats.write_csv(&mut output).unwrap();
+
+    assert_eq!(
+      String::from_utf8(output).unwrap(),
+      "window_start,row,package_kind,produced,delivered,lost,conveyor_x,conveyor_y,busy_ticks,sampled_ticks\n\
+        0,package,3,1,0,0,,,,\n\
+        0,conveyor,,,,,0,0,1,2\n\
+        0,conveyor,,,,,1,0,1,1\n",
+    );
+  }
+}
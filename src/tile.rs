@@ -1,34 +1,56 @@
-use std::fmt::Display;
-
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_ecs_tilemap::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::helpers::grid_traversal::GridTraversal;
+use crate::economy::{BuildBudget, Purchased};
 use crate::input::chained_tile::{ChainedTileChangeEvent, ChainedTilePlaceDirection, ChainedTileChangePosition};
 use crate::input::prelude::*;
+use crate::localisation::LocalisedName;
 use crate::GameSystemSet;
 use crate::vec2_traits::TilePosFromSigned;
 
+use self::animation::plugin_exports::*;
+use self::arrows::plugin_exports::*;
+use self::autotile::plugin_exports::*;
 use self::background::plugin_exports::*;
 use self::placement::plugin_exports::*;
+use self::overlay::plugin_exports::*;
 use self::removal::plugin_exports::*;
+use self::save::plugin_exports::*;
+use self::validation::plugin_exports::*;
 use self::update_graphics::systems::*;
 use self::playfield::plugin_exports::*;
 
+pub mod animation;
+pub mod autotile;
+pub mod network;
+pub mod overlay;
 pub mod placement;
 pub mod removal;
+pub mod save;
 pub mod update_graphics;
+pub mod validation;
+mod arrows;
 mod background;
 mod playfield;
 
 pub mod prelude {
   pub use super::ConveyorBuildPlugin;
   pub use super::ConveyorDirection;
+  pub use super::TileCategory;
+  pub use super::TileEdit;
+  pub use super::TileEdited;
+  pub use super::TileType;
   pub use super::UpdatedTile;
+  pub use super::overlay::prelude::*;
   pub use super::playfield::prelude::*;
+  pub use super::save::prelude::*;
+  pub use super::validation::prelude::*;
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Component, Reflect)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub enum ConveyorDirection {
   North,
   South,
@@ -36,15 +58,96 @@ pub enum ConveyorDirection {
   West,
 }
 
+// The kinds of tile the player can build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub enum TileType {
+  #[default]
+  Conveyor,
+  FastConveyor,
+  ExpressConveyor,
+}
+
+// Groups of tile types, shown as tabs of the build menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum TileCategory {
+  #[default]
+  Transport,
+}
+
+impl TileCategory {
+  pub const VALUES: [TileCategory; 1] = [TileCategory::Transport];
+}
+
+impl LocalisedName for TileCategory {
+  fn name_key(&self) -> &'static str {
+    match self {
+      TileCategory::Transport => "category.transport",
+    }
+  }
+}
+
+impl TileType {
+  // In build menu order, which the number keys follow.
+  pub const VALUES: [TileType; 3] = [TileType::Conveyor, TileType::FastConveyor, TileType::ExpressConveyor];
+
+  pub fn category(&self) -> TileCategory {
+    match self {
+      TileType::Conveyor | TileType::FastConveyor | TileType::ExpressConveyor => TileCategory::Transport,
+    }
+  }
+
+  // Frame of the tile's icon in `conveyor.aseprite`.
+  pub fn icon_index(&self) -> u32 {
+    match self {
+      TileType::Conveyor | TileType::FastConveyor | TileType::ExpressConveyor => ConveyorDirection::North.texture_index(),
+    }
+  }
+
+  // Tints the belts of each speed on the map and in the UI, so the tiers tell apart
+  // without watching them scroll.
+  pub fn tint(&self) -> Color {
+    match self {
+      TileType::Conveyor => Color::WHITE,
+      TileType::FastConveyor => Color::rgb(0.55, 0.8, 1.0),
+      TileType::ExpressConveyor => Color::rgb(1.0, 0.6, 0.45),
+    }
+  }
+
+  // How many times faster than a plain conveyor the belt carries packages and scrolls.
+  pub fn belt_speed(&self) -> u32 {
+    match self {
+      TileType::Conveyor => 1,
+      TileType::FastConveyor => 2,
+      TileType::ExpressConveyor => 3,
+    }
+  }
+}
+
+impl LocalisedName for TileType {
+  fn name_key(&self) -> &'static str {
+    match self {
+      TileType::Conveyor => "tile.conveyor",
+      TileType::FastConveyor => "tile.fast_conveyor",
+      TileType::ExpressConveyor => "tile.express_conveyor",
+    }
+  }
+}
+
 impl Default for ConveyorDirection {
   fn default() -> Self {
     ConveyorDirection::North
   }
 }
 
-impl Display for ConveyorDirection {
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    f.write_str(self.name())
+impl LocalisedName for ConveyorDirection {
+  fn name_key(&self) -> &'static str {
+    match self {
+      ConveyorDirection::North => "direction.north",
+      ConveyorDirection::East => "direction.east",
+      ConveyorDirection::South => "direction.south",
+      ConveyorDirection::West => "direction.west",
+    }
   }
 }
 
@@ -78,15 +181,6 @@ impl ConveyorDirection {
     self.reverse(direction == ChainedTilePlaceDirection::Revesed)
   }
 
-  pub fn name(&self) -> &'static str {
-    match self {
-      ConveyorDirection::North => "North",
-      ConveyorDirection::East => "East",
-      ConveyorDirection::South => "South",
-      ConveyorDirection::West => "West",
-    }
-  }
-
   pub const DIRECTION_VALUES: [IVec2; 4] = [IVec2::Y, IVec2::X, IVec2::NEG_Y, IVec2::NEG_X];
 
   pub fn neighbor_offsets(&self) -> [IVec2; 3] {
@@ -181,24 +275,10 @@ impl ConveyorBuildPlugin {
   pub fn new_no_background(playfield_size: PlayfieldSize) -> ConveyorBuildPlugin {
     ConveyorBuildPlugin { playfield_size, include_background: false, include_textures: true, }
   }
-}
-
-#[derive(Default, Resource)]
-struct UiState {
-  // egui_texture_handle: Option<egui::TextureHandle>,
-  conveyor_atlas: Option<Handle<TextureAtlas>>,
-}
 
-fn setup_conveyor_ui(
-  mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-  asset_server: Res<AssetServer>,
-  mut ui_state: ResMut<UiState>,
-) {
-  let conveyor_texture = asset_server.load("conveyor.png");
-  let texture_atlas =
-    TextureAtlas::from_grid(conveyor_texture, Vec2::new(16.0, 16.0), 29, 1, None, None);
-  let texture_atlas_handle = texture_atlases.add(texture_atlas);
-  ui_state.conveyor_atlas = Some(texture_atlas_handle);
+  pub fn new_headless(playfield_size: PlayfieldSize) -> ConveyorBuildPlugin {
+    ConveyorBuildPlugin { playfield_size, include_background: false, include_textures: false, }
+  }
 }
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
@@ -211,11 +291,16 @@ impl Plugin for ConveyorBuildPlugin {
   fn build(&self, app: &mut bevy::prelude::App) {
     app
       .init_resource::<PreviousPlaceAttempt>()
+      .init_resource::<AutotileRules>()
+      .init_resource::<BeltAnimation>()
       .insert_resource(self.playfield_size.clone())
       .add_event::<UpdatedTile>()
+      .add_event::<TileEdited>()
       .add_event::<ChainedTileChangeEvent>()
+      .add_event::<PlacementRejected>()
       .add_startup_system(setup_playfield.in_set(TileSetupSystemSet::SpawnTilemaps))
       .add_startup_system(apply_system_buffers.after(TileSetupSystemSet::SpawnTilemaps).before(TileSetupSystemSet::InsertTileData))
+      .add_startup_system(place_saved_layout.in_set(TileSetupSystemSet::InsertTileData))
       .add_systems(
         (
           catch_chained_tile_change_events,
@@ -224,17 +309,33 @@ impl Plugin for ConveyorBuildPlugin {
         )
           .in_set(GameSystemSet::TilePlacing)
           .chain()
-      );
+      )
+      .add_system(animate_belts.in_set(GameSystemSet::PostTilePlacing))
+      // jam and bottleneck overlay
+      .init_resource::<ConveyorOverlay>()
+      .init_resource::<HighlightedTiles>()
+      .add_systems((update_conveyor_overlay, highlight_tiles).in_set(GameSystemSet::PostTilePlacing).chain())
+      // layout validation
+      .init_resource::<LayoutValidation>()
+      .add_system(update_layout_validation.in_set(GameSystemSet::PostTilePlacing));
 
     if self.include_background {
       app.add_startup_system(setup_background_tilemap.in_set(TileSetupSystemSet::SpawnTilemaps));
       if self.include_textures {
-        app.add_startup_systems((insert_background_texture, place_background_tiles).in_set(TileSetupSystemSet::InsertTileData));
+        app
+          .add_startup_systems((insert_background_texture, place_background_tiles).in_set(TileSetupSystemSet::InsertTileData))
+          .add_system(apply_background_palette.in_set(GameSystemSet::PostTilePlacing));
       }
     }
 
     if self.include_textures {
-      app.add_startup_system(insert_playfield_texture.in_set(TileSetupSystemSet::InsertTileData));
+      app
+        .add_startup_system(load_autotile_rules.in_set(TileSetupSystemSet::SpawnTilemaps))
+        .add_startup_system(insert_playfield_texture.in_set(TileSetupSystemSet::InsertTileData))
+        .add_system(size_belt_animation.in_set(GameSystemSet::PostTilePlacing).before(animate_belts))
+        // direction arrows
+        .add_startup_system(setup_direction_arrow_tilemap.in_set(TileSetupSystemSet::SpawnTilemaps))
+        .add_system(update_direction_arrows.in_set(GameSystemSet::PostTilePlacing));
     }
 
     if !app.world.is_resource_added::<SelectedTileDirection>() {
@@ -248,13 +349,34 @@ pub struct UpdatedTile {
   pub pos: TilePos,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileEdit {
+  Placed(TileType),
+  Removed,
+  Rotated,
+}
+
+// Sent for every tile the player changes, unlike `UpdatedTile` which is also sent for
+// tiles whose graphics change along with their neighbours.
+#[derive(Debug, Clone)]
+pub struct TileEdited {
+  pub pos: TilePos,
+  pub edit: TileEdit,
+}
+
+#[derive(SystemParam)]
+pub struct TileChangeEvents<'w> {
+  updated_tiles: EventWriter<'w, UpdatedTile>,
+  edited_tiles: EventWriter<'w, TileEdited>,
+}
+
 pub fn catch_chained_tile_change_events(
   mut commands: Commands,
   mut place_tile_events: EventReader<ChainedTileChangeEvent>,
-  mut placed_tiles: EventWriter<UpdatedTile>,
+  mut tile_events: TileChangeEvents,
   mut tilemap: Query<(Entity, &mut TileStorage, &TilemapSize, &ConveyorTileLayer)>,
-  mut previous_tile_attempt: ResMut<PreviousPlaceAttempt>,
-  mut selected_tile_rotation: ResMut<SelectedTileDirection>,
+  mut tile_chain: TileChain,
+  mut build_budget: BuildBudget,
 ) {
   let Ok((tilemap_entity, mut tile_storage, tilemap_size, _)) = tilemap.get_single_mut() else { 
     error!(
@@ -272,12 +394,38 @@ pub fn catch_chained_tile_change_events(
 
     for position in positions {
       match place_tile_event.change_type {
-        crate::input::chained_tile::ChainedTileChangeType::Put { tile_type: _tile_type, chain, direction } => {
-          place_tile(&mut commands, position, &mut tile_storage, tilemap_entity, tilemap_size, &mut previous_tile_attempt, &mut placed_tiles, direction, &mut selected_tile_rotation.direction, chain);
+        crate::input::chained_tile::ChainedTileChangeType::Put { tile_type, chain, direction } => {
+          let placement = ChainedPlacement { position, tile_type, place_direction: direction, chain_with_previous_tile: chain };
+          let tile_pos = position.to_tile_pos(tilemap_size).ok();
+          if let Some(tile_pos) = tile_pos {
+            if !build_budget.try_build(&tile_storage, tile_pos, tile_type) {
+              continue_chain(&mut commands, &placement, &tile_storage, tilemap_size, &mut tile_chain, &mut tile_events.updated_tiles);
+              continue;
+            }
+          }
+          place_tile(&mut commands, &placement, &mut tile_storage, tilemap_entity, tilemap_size, &mut tile_chain, &mut tile_events.updated_tiles);
+          if let Some(pos) = tile_pos {
+            if let Some(tile_entity) = tile_storage.get(&pos) {
+              commands.entity(tile_entity).insert(Purchased);
+            }
+            tile_events.edited_tiles.send(TileEdited { pos, edit: TileEdit::Placed(tile_type) });
+          }
         },
         crate::input::chained_tile::ChainedTileChangeType::Delete => {
           if let Ok(position) = position.to_tile_pos(&tilemap_size) {
-            despawn_conveyor(&mut commands, position, &mut tile_storage, &mut placed_tiles);
+            if let Some(tile_entity) = tile_storage.get(&position) {
+              build_budget.refund(tile_entity);
+              tile_events.edited_tiles.send(TileEdited { pos: position, edit: TileEdit::Removed });
+            }
+            despawn_conveyor(&mut commands, position, &mut tile_storage, &mut tile_events.updated_tiles);
+          }
+        },
+        crate::input::chained_tile::ChainedTileChangeType::Rotate { direction } => {
+          if let Ok(position) = position.to_tile_pos(tilemap_size) {
+            if tile_storage.get(&position).is_some() {
+              tile_events.edited_tiles.send(TileEdited { pos: position, edit: TileEdit::Rotated });
+            }
+            update_tile_direction(&mut commands, position, &tile_storage, direction, &mut tile_events.updated_tiles);
           }
         },
       }
@@ -349,4 +497,157 @@ mod tile_test {
 
     assert!(tiles.next().is_none());
   }
+
+  #[test]
+  fn place_over_existing_conveyor() {
+    let mut app = App::new();
+
+    app.add_plugin(ConveyorBuildPlugin { playfield_size: PlayfieldSize(UVec2::new(8, 8)), include_background: false, include_textures: false});
+
+    app.setup();
+
+    app.update();
+
+    let put = || ChainedTileChangeEvent {
+      position: ChainedTileChangePosition::Single(IVec2::ONE),
+      change_type: ChainedTileChangeType::Put { tile_type: TileType::Conveyor, chain: false, direction: ChainedTilePlaceDirection::Normal }
+    };
+    app.world.send_event(put());
+    app.update();
+    let first_entity = app.world.query::<(Entity, &ConveyorDirection)>().single(&app.world).0;
+
+    app.world.resource_mut::<SelectedTileDirection>().direction = ConveyorDirection::East;
+    app.world.send_event(put());
+    app.update();
+
+    let (entity, conveyor_direction) = app.world.query::<(Entity, &ConveyorDirection)>().single(&app.world);
+    assert_eq!(entity, first_entity);
+    assert_eq!(*conveyor_direction, ConveyorDirection::East);
+  }
+
+  #[test]
+  fn rotate_conveyor() {
+    let mut app = App::new();
+
+    app.add_plugin(ConveyorBuildPlugin { playfield_size: PlayfieldSize(UVec2::new(8, 8)), include_background: false, include_textures: false});
+
+    app.setup();
+
+    app.update();
+
+    app.world.send_event(ChainedTileChangeEvent {
+      position: ChainedTileChangePosition::Single(IVec2::ONE),
+      change_type: ChainedTileChangeType::Put { tile_type: TileType::Conveyor, chain: false, direction: ChainedTilePlaceDirection::Normal }
+    });
+    app.update();
+
+    app.world.send_event(ChainedTileChangeEvent {
+      position: ChainedTileChangePosition::Single(IVec2::ONE),
+      change_type: ChainedTileChangeType::Rotate { direction: ConveyorDirection::West }
+    });
+    app.update();
+
+    let mut tiles = app.world.query::<(&TilePos, &ConveyorDirection)>();
+    let (tile_pos, conveyor_direction) = tiles.single(&app.world);
+    assert_eq!(*tile_pos, TilePos { x: 1, y: 1 });
+    assert_eq!(*conveyor_direction, ConveyorDirection::West);
+  }
+
+  #[test]
+  fn edits_are_reported() {
+    let mut app = App::new();
+
+    app.add_plugin(ConveyorBuildPlugin { playfield_size: PlayfieldSize(UVec2::new(8, 8)), include_background: false, include_textures: false});
+
+    app.setup();
+
+    app.update();
+
+    let mut edits = app.world.resource::<Events<TileEdited>>().get_reader();
+    let mut read_edits = |app: &App| -> Vec<_> {
+      let edited_tiles = app.world.resource::<Events<TileEdited>>();
+      edits.iter(edited_tiles).map(|edited| (edited.pos.x, edited.edit)).collect()
+    };
+
+    // the start of a line was placed when the drag started, and the part outside the
+    // playfield doesn't exist
+    app.world.send_event(ChainedTileChangeEvent {
+      position: ChainedTileChangePosition::StraightLine { start: IVec2::new(5, 1), end: IVec2::new(9, 1) },
+      change_type: ChainedTileChangeType::Put { tile_type: TileType::Conveyor, chain: false, direction: ChainedTilePlaceDirection::Normal }
+    });
+    app.update();
+    assert_eq!(read_edits(&app), vec![(6, TileEdit::Placed(TileType::Conveyor)), (7, TileEdit::Placed(TileType::Conveyor))]);
+
+    // removing an empty tile isn't an edit
+    app.world.send_event(ChainedTileChangeEvent {
+      position: ChainedTileChangePosition::StraightLine { start: IVec2::new(6, 0), end: IVec2::new(6, 3) },
+      change_type: ChainedTileChangeType::Delete
+    });
+    app.update();
+    assert_eq!(read_edits(&app), vec![(6, TileEdit::Removed)]);
+  }
+
+  #[test]
+  fn refused_tiles_keep_the_chain_going() {
+    let mut app = App::new();
+
+    app.add_plugin(ConveyorBuildPlugin { playfield_size: PlayfieldSize(UVec2::new(8, 8)), include_background: false, include_textures: false});
+    app.insert_resource(crate::economy::Wallet::new(crate::economy::CONVEYOR_COST));
+
+    app.setup();
+
+    app.update();
+
+    let put = |position: ChainedTileChangePosition, chain: bool| ChainedTileChangeEvent {
+      position,
+      change_type: ChainedTileChangeType::Put { tile_type: TileType::Conveyor, chain, direction: ChainedTilePlaceDirection::Normal }
+    };
+    app.world.send_event(put(ChainedTileChangePosition::Single(IVec2::ONE), false));
+    app.update();
+
+    // there's no money left for the second tile, but the first one still turns towards it
+    app.world.send_event(put(ChainedTileChangePosition::StraightLine { start: IVec2::ONE, end: IVec2::new(2, 1) }, true));
+    app.update();
+
+    let (tile_pos, conveyor_direction) = app.world.query::<(&TilePos, &ConveyorDirection)>().single(&app.world);
+    assert_eq!(*tile_pos, TilePos { x: 1, y: 1 });
+    assert_eq!(*conveyor_direction, ConveyorDirection::East);
+    assert_eq!(app.world.resource::<PreviousPlaceAttempt>().position, IVec2::new(2, 1));
+  }
+
+  #[test]
+  fn speed_tiers_are_tinted() {
+    let mut app = App::new();
+
+    app.add_plugin(ConveyorBuildPlugin { playfield_size: PlayfieldSize(UVec2::new(8, 8)), include_background: false, include_textures: false});
+
+    app.setup();
+
+    app.update();
+
+    let put = |tile_type: TileType| ChainedTileChangeEvent {
+      position: ChainedTileChangePosition::Single(IVec2::ONE),
+      change_type: ChainedTileChangeType::Put { tile_type, chain: false, direction: ChainedTilePlaceDirection::Normal }
+    };
+    let tile_color = |app: &mut App| app.world.query::<&TileColor>().single(&app.world).0;
+
+    app.world.send_event(put(TileType::Conveyor));
+    app.update();
+    assert_eq!(tile_color(&mut app), TileType::Conveyor.tint());
+
+    app.world.send_event(put(TileType::FastConveyor));
+    app.update();
+    assert_eq!(tile_color(&mut app), TileType::FastConveyor.tint());
+
+    // turning the overlay off brings back the tier's tint
+    app.world.resource_mut::<ConveyorOverlay>().enabled = true;
+    app.update();
+    assert_ne!(tile_color(&mut app), TileType::FastConveyor.tint());
+    app.world.resource_mut::<ConveyorOverlay>().enabled = false;
+    app.update();
+    assert_eq!(tile_color(&mut app), TileType::FastConveyor.tint());
+
+    let tints: Vec<_> = TileType::VALUES.iter().map(|tile_type| tile_type.tint().as_rgba_f32()).collect();
+    assert!(tints.iter().enumerate().all(|(index, tint)| !tints[index + 1..].contains(tint)));
+  }
 }
@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::simulation::prelude::*;
+
+use super::{autotile::AutotileRules, ConveyorTileLayer, TileType};
+
+pub mod plugin_exports {
+  pub use super::animate_belts;
+  pub use super::size_belt_animation;
+  pub use super::BeltAnimation;
+}
+
+// First animation frame of a conveyor, as chosen by the autotile rules.
+#[derive(Debug, Component, Clone, Copy, Default)]
+pub struct AutotileBase(pub u32);
+
+// The simulation tick belts were last drawn for.
+#[derive(Debug, Resource, Default)]
+pub struct BeltAnimation {
+  pub tick: u64,
+}
+
+// Sizes the belt animation's stride from the conveyor texture once it has loaded, so the
+// animation frames always line up with the atlas they're drawn from.
+pub fn size_belt_animation(
+  mut image_events: EventReader<AssetEvent<Image>>,
+  images: Res<Assets<Image>>,
+  tilemap: Query<(&TilemapTexture, &TilemapTileSize), With<ConveyorTileLayer>>,
+  mut autotile_rules: ResMut<AutotileRules>,
+) {
+  let Ok((TilemapTexture::Single(texture), tile_size)) = tilemap.get_single() else { return; };
+  for event in image_events.iter() {
+    let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else { continue; };
+    if handle != texture {
+      continue;
+    }
+    let Some(image) = images.get(handle) else { continue; };
+    let atlas_frames = (image.size().x / tile_size.x) as u32;
+    if let Err(error) = autotile_rules.size_animation(atlas_frames) {
+      error!("Could not animate the conveyor belts: {}", error);
+    }
+  }
+}
+
+// Moves every belt to the frame for the current simulation tick and its speed, so belts
+// stop while the simulation is paused and speed up with it.
+pub fn animate_belts(
+  simulation_time: Option<Res<SimulationTime>>,
+  autotile_rules: Res<AutotileRules>,
+  mut animation: ResMut<BeltAnimation>,
+  mut tiles: Query<(&AutotileBase, &TileType, &mut TileTextureIndex)>,
+) {
+  let tick = simulation_time.map_or(0, |time| time.tick());
+  if tick == animation.tick && !autotile_rules.is_changed() {
+    return;
+  }
+  animation.tick = tick;
+  for (base, tile_type, mut texture_index) in tiles.iter_mut() {
+    let frame = autotile_rules.animation.frame_at(tick, tile_type.belt_speed());
+    let wanted_index = autotile_rules.animated_index(base.0, frame);
+    if texture_index.0 != wanted_index {
+      texture_index.0 = wanted_index;
+    }
+  }
+}
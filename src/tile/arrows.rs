@@ -0,0 +1,253 @@
+use bevy::{prelude::*, render::render_resource::{Extent3d, TextureDimension, TextureFormat}};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::settings::prelude::*;
+
+use super::{network::tile_positions, ConveyorDirection, ConveyorTileLayer, PlayfieldSize, UpdatedTile};
+
+pub mod plugin_exports {
+  pub use super::setup_direction_arrow_tilemap;
+  pub use super::update_direction_arrows;
+}
+
+const ARROW_TILE_SIZE: u32 = 16;
+// Frames of the arrow texture, left to right.
+const ARROW_DIRECTIONS: [ConveyorDirection; 4] = [
+  ConveyorDirection::North,
+  ConveyorDirection::East,
+  ConveyorDirection::South,
+  ConveyorDirection::West,
+];
+const ARROW_FILL: [u8; 4] = [255, 255, 255, 255];
+const ARROW_OUTLINE: [u8; 4] = [0, 0, 0, 255];
+
+// Arrows drawn over every conveyor, so the way a belt runs doesn't depend on reading
+// its sprite.
+#[derive(Debug, Component)]
+pub struct DirectionArrowLayer;
+
+fn arrow_texture_index(direction: ConveyorDirection) -> u32 {
+  ARROW_DIRECTIONS.iter().position(|arrow| *arrow == direction).unwrap_or_default() as u32
+}
+
+// Whether a pixel belongs to an arrow, given its distance from the tile centre along
+// and across the arrow.
+fn is_arrow_pixel(along: f32, across: f32) -> bool {
+  let shaft = (-4.0..0.0).contains(&along) && across.abs() <= 1.0;
+  let head = (0.0..4.0).contains(&along) && across.abs() <= 3.5 - along;
+  shaft || head
+}
+
+fn arrow_pixel(direction: ConveyorDirection, x: i32, y: i32) -> bool {
+  if !(0..ARROW_TILE_SIZE as i32).contains(&x) || !(0..ARROW_TILE_SIZE as i32).contains(&y) {
+    return false;
+  }
+  // image rows run downwards, so flip y to match the direction offsets
+  let half = ARROW_TILE_SIZE as f32 / 2.0;
+  let position = Vec2::new(x as f32 + 0.5 - half, half - (y as f32 + 0.5));
+  let forward = direction.offset().as_vec2();
+  is_arrow_pixel(position.dot(forward), position.perp_dot(forward))
+}
+
+// One outlined arrow per direction, side by side.
+fn arrow_image_data() -> Vec<u8> {
+  let size = ARROW_TILE_SIZE as i32;
+  let width = size * ARROW_DIRECTIONS.len() as i32;
+  let mut data = vec![0; (width * size * 4) as usize];
+  for (frame, direction) in ARROW_DIRECTIONS.iter().enumerate() {
+    for y in 0..size {
+      for x in 0..size {
+        let touches_arrow = (-1..=1).any(|dy| (-1..=1).any(|dx| arrow_pixel(*direction, x + dx, y + dy)));
+        let color = match (arrow_pixel(*direction, x, y), touches_arrow) {
+          (true, _) => ARROW_FILL,
+          (false, true) => ARROW_OUTLINE,
+          (false, false) => continue,
+        };
+        let offset = ((y * width + frame as i32 * size + x) * 4) as usize;
+        data[offset..offset + 4].copy_from_slice(&color);
+      }
+    }
+  }
+  data
+}
+
+pub fn setup_direction_arrow_tilemap(
+  mut commands: Commands,
+  mut images: ResMut<Assets<Image>>,
+  playfield_size: Res<PlayfieldSize>,
+) {
+  let tile_size = TilemapTileSize { x: ARROW_TILE_SIZE as f32, y: ARROW_TILE_SIZE as f32 };
+  let grid_size = tile_size.into();
+  let map_type = TilemapType::Square;
+  let map_size = TilemapSize { x: playfield_size.0.x, y: playfield_size.0.y };
+
+  let image = images.add(Image::new(
+    Extent3d { width: ARROW_TILE_SIZE * ARROW_DIRECTIONS.len() as u32, height: ARROW_TILE_SIZE, depth_or_array_layers: 1 },
+    TextureDimension::D2,
+    arrow_image_data(),
+    TextureFormat::Rgba8UnormSrgb,
+  ));
+
+  commands
+    .spawn(TilemapBundle {
+      grid_size,
+      map_type,
+      size: map_size,
+      storage: TileStorage::empty(map_size),
+      texture: TilemapTexture::Single(image),
+      tile_size,
+      transform: get_tilemap_center_transform(&map_size, &grid_size, &map_type, 15.0),
+      visibility: Visibility::Hidden,
+      ..default()
+    })
+    .insert(DirectionArrowLayer);
+}
+
+// Keeps an arrow on top of every conveyor while they're turned on in the settings. Only
+// the tiles which changed are looked at, apart from when the arrows are turned on.
+pub fn update_direction_arrows(
+  mut commands: Commands,
+  settings: Option<Res<Settings>>,
+  mut updated_tiles: EventReader<UpdatedTile>,
+  conveyor_layer: Query<&TileStorage, (With<ConveyorTileLayer>, Without<DirectionArrowLayer>)>,
+  mut arrow_layer: Query<(Entity, &mut TileStorage, &mut Visibility), With<DirectionArrowLayer>>,
+  conveyors: Query<&ConveyorDirection>,
+  mut arrows: Query<&mut TileTextureIndex>,
+) {
+  let (Ok(conveyor_storage), Ok((arrow_layer_entity, mut arrow_storage, mut visibility))) =
+    (conveyor_layer.get_single(), arrow_layer.get_single_mut()) else { return; };
+
+  let enabled = settings.is_some_and(|settings| settings.direction_arrows);
+  let wanted_visibility = if enabled { Visibility::Inherited } else { Visibility::Hidden };
+  let turned_on = enabled && *visibility != wanted_visibility;
+  if *visibility != wanted_visibility {
+    *visibility = wanted_visibility;
+  }
+  if !enabled {
+    updated_tiles.clear();
+    return;
+  }
+
+  let positions: Vec<_> = match turned_on {
+    true => {
+      updated_tiles.clear();
+      tile_positions(&conveyor_storage.size).collect()
+    }
+    false => updated_tiles.iter().map(|updated| updated.pos).collect(),
+  };
+
+  for pos in positions {
+    let direction = conveyor_storage.checked_get(&pos).and_then(|entity| conveyors.get(entity).ok());
+    match (direction, arrow_storage.checked_get(&pos)) {
+      (Some(direction), Some(arrow)) => {
+        let Ok(mut texture_index) = arrows.get_mut(arrow) else { continue; };
+        let wanted_index = arrow_texture_index(*direction);
+        if texture_index.0 != wanted_index {
+          texture_index.0 = wanted_index;
+        }
+      }
+      (Some(direction), None) => {
+        let arrow = commands
+          .spawn(TileBundle {
+            position: pos,
+            tilemap_id: TilemapId(arrow_layer_entity),
+            texture_index: TileTextureIndex(arrow_texture_index(*direction)),
+            ..default()
+          })
+          .id();
+        arrow_storage.set(&pos, arrow);
+      }
+      (None, Some(arrow)) => {
+        commands.entity(arrow).despawn();
+        arrow_storage.remove(&pos);
+      }
+      (None, None) => {}
+    }
+  }
+}
+
+#[cfg(test)]
+mod arrows_test {
+  use crate::{
+    input::{chained_tile::{ChainedTileChangeEvent, ChainedTileChangePosition, ChainedTileChangeType, ChainedTilePlaceDirection}, prelude::*},
+    tile::{prelude::*, ConveyorBuildPlugin},
+    GameSystemSet,
+  };
+
+  use super::*;
+
+  fn arrows_app() -> App {
+    let mut app = App::new();
+    app
+      .add_plugins(MinimalPlugins)
+      .add_plugin(ConveyorBuildPlugin::new_headless(PlayfieldSize(UVec2::new(4, 4))))
+      .init_resource::<Settings>()
+      .add_system(update_direction_arrows.in_set(GameSystemSet::PostTilePlacing))
+      .configure_sets(GameSystemSet::configure_sets());
+    let map_size = TilemapSize { x: 4, y: 4 };
+    app.world.spawn((TileStorage::empty(map_size), Visibility::Hidden, DirectionArrowLayer));
+    app.setup();
+    app.update();
+    app
+  }
+
+  fn put_conveyor(app: &mut App, x: i32, direction: ConveyorDirection) {
+    app.world.resource_mut::<SelectedTileDirection>().direction = direction;
+    app.world.send_event(ChainedTileChangeEvent {
+      position: ChainedTileChangePosition::Single(IVec2::new(x, 0)),
+      change_type: ChainedTileChangeType::Put { tile_type: TileType::Conveyor, chain: false, direction: ChainedTilePlaceDirection::Normal },
+    });
+    app.update();
+  }
+
+  fn arrow_indices(app: &mut App) -> Vec<u32> {
+    let mut indices: Vec<_> = app.world.query::<(&TilePos, &TileTextureIndex, &TilemapId)>()
+      .iter(&app.world)
+      .filter(|(_, _, tilemap_id)| app.world.get::<DirectionArrowLayer>(tilemap_id.0).is_some())
+      .map(|(pos, texture_index, _)| (pos.x, texture_index.0))
+      .collect();
+    indices.sort();
+    indices.into_iter().map(|(_, index)| index).collect()
+  }
+
+  #[test]
+  fn arrows_follow_updated_tiles() {
+    let mut app = arrows_app();
+    put_conveyor(&mut app, 1, ConveyorDirection::North);
+    assert!(arrow_indices(&mut app).is_empty());
+
+    // conveyors placed while the arrows were off get theirs once turned on
+    app.world.resource_mut::<Settings>().direction_arrows = true;
+    app.update();
+    assert_eq!(arrow_indices(&mut app), vec![arrow_texture_index(ConveyorDirection::North)]);
+
+    put_conveyor(&mut app, 2, ConveyorDirection::East);
+    put_conveyor(&mut app, 1, ConveyorDirection::West);
+    assert_eq!(
+      arrow_indices(&mut app),
+      vec![arrow_texture_index(ConveyorDirection::West), arrow_texture_index(ConveyorDirection::East)]
+    );
+  }
+
+  #[test]
+  fn arrows_point_the_right_way() {
+    // the tip sits next to the edge the conveyor leads to
+    assert!(arrow_pixel(ConveyorDirection::East, 10, 7));
+    assert!(!arrow_pixel(ConveyorDirection::East, 5, 5));
+    assert!(arrow_pixel(ConveyorDirection::North, 5, 7));
+    assert!(!arrow_pixel(ConveyorDirection::North, 5, 8));
+    assert!(arrow_pixel(ConveyorDirection::West, 5, 8));
+    assert!(arrow_pixel(ConveyorDirection::South, 8, 10));
+  }
+
+  #[test]
+  fn arrows_are_outlined() {
+    let data = arrow_image_data();
+    let width = ARROW_TILE_SIZE * ARROW_DIRECTIONS.len() as u32;
+    let pixel = |x: u32, y: u32| &data[((y * width + x) * 4) as usize..((y * width + x) * 4 + 4) as usize];
+    let east = ARROW_TILE_SIZE;
+    assert_eq!(pixel(east + 10, 7), ARROW_FILL);
+    assert_eq!(pixel(east + 11, 7), ARROW_OUTLINE);
+    assert_eq!(pixel(east + 13, 7), [0, 0, 0, 0]);
+  }
+}
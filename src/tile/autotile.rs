@@ -0,0 +1,301 @@
+use std::path::Path;
+
+use bevy::{asset::FileAssetIo, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{update_graphics::ConveyorNeighbor, ConveyorDirection};
+
+pub mod plugin_exports {
+  pub use super::load_autotile_rules;
+  pub use super::AutotileRules;
+}
+
+pub const TILESET_PATH: &str = "conveyor.tileset.json";
+const BUILT_IN_TILESET: &str = include_str!("../../assets/conveyor.tileset.json");
+
+// How a rule's frame is turned to face the conveyor's direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleRotation {
+  // The atlas has four frames from `index` on, facing north, east, south and west.
+  #[default]
+  Frames,
+  // The atlas has a single frame facing north, which is rotated by flipping it the way Tiled does.
+  Flip,
+}
+
+// Which sides of a conveyor, as seen facing its direction, have another conveyor pointing
+// into them, and the frame to draw for that.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutotileRule {
+  pub right: bool,
+  pub back: bool,
+  pub left: bool,
+  pub index: u32,
+  #[serde(default)]
+  pub rotation: RuleRotation,
+}
+
+impl AutotileRule {
+  fn mask(&self) -> usize {
+    mask_index([self.right, self.back, self.left])
+  }
+}
+
+// Scrolling belt frames. The atlas holds `frames` copies of the tileset side by side, so
+// frame `n` of a tile is found `n * stride` past its rule's frame. The stride is sized
+// from the atlas once it has loaded, and stays 0 until then, showing the first frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnimationDefinition {
+  pub frames: u32,
+  #[serde(skip)]
+  pub stride: u32,
+  // For the slowest belts, faster belts go through their frames as many times faster.
+  pub ticks_per_frame: u32,
+}
+
+impl Default for AnimationDefinition {
+  fn default() -> Self {
+    AnimationDefinition { frames: 1, stride: 0, ticks_per_frame: 1 }
+  }
+}
+
+impl AnimationDefinition {
+  // Frame every belt of a speed shows at a simulation tick, so they all move in step and
+  // speed up with the simulation.
+  pub fn frame_at(&self, tick: u64, belt_speed: u32) -> u32 {
+    ((tick * belt_speed as u64 / self.ticks_per_frame as u64) % self.frames as u64) as u32
+  }
+}
+
+// A belt tileset: the texture to draw conveyors with and which of its frames to use for
+// every combination of inputs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TilesetDefinition {
+  pub texture: String,
+  pub rules: Vec<AutotileRule>,
+  #[serde(default)]
+  pub animation: AnimationDefinition,
+}
+
+#[derive(Debug)]
+pub enum TilesetError {
+  Io(std::io::Error),
+  Format(serde_json::Error),
+  // Combinations of inputs, as [right, back, left], that no rule covers.
+  MissingMasks(Vec<[bool; 3]>),
+  DuplicateMask([bool; 3]),
+  // Animations need at least one frame, shown for at least one tick.
+  EmptyAnimation,
+  // The atlas can't be split into a copy of the tileset per animation frame.
+  UnevenAtlas { atlas_frames: u32, animation_frames: u32 },
+}
+
+impl std::fmt::Display for TilesetError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      TilesetError::Io(error) => write!(f, "{}", error),
+      TilesetError::Format(error) => write!(f, "invalid tileset definition: {}", error),
+      TilesetError::MissingMasks(masks) => write!(f, "no rule for inputs (right, back, left) {:?}", masks),
+      TilesetError::DuplicateMask(mask) => write!(f, "more than one rule for inputs (right, back, left) {:?}", mask),
+      TilesetError::EmptyAnimation => write!(f, "animation frames and ticks_per_frame have to be at least 1"),
+      TilesetError::UnevenAtlas { atlas_frames, animation_frames } => {
+        write!(f, "the atlas has {} frames, which can't be split into {} animation frames", atlas_frames, animation_frames)
+      }
+    }
+  }
+}
+
+const MASK_COUNT: usize = 8;
+
+fn mask_index(inputs: [bool; 3]) -> usize {
+  inputs.iter().enumerate().map(|(bit, input)| (*input as usize) << bit).sum()
+}
+
+fn mask_inputs(index: usize) -> [bool; 3] {
+  [index & 1 != 0, index & 2 != 0, index & 4 != 0]
+}
+
+// Validated autotile rules with exactly one rule for every combination of inputs.
+#[derive(Debug, Resource, Clone)]
+pub struct AutotileRules {
+  pub texture: String,
+  pub animation: AnimationDefinition,
+  rules: [(u32, RuleRotation); MASK_COUNT],
+}
+
+impl Default for AutotileRules {
+  fn default() -> Self {
+    AutotileRules::parse(BUILT_IN_TILESET).expect("the built-in tileset definition should be valid")
+  }
+}
+
+impl AutotileRules {
+  pub fn new(definition: TilesetDefinition) -> Result<AutotileRules, TilesetError> {
+    if definition.animation.frames == 0 || definition.animation.ticks_per_frame == 0 {
+      return Err(TilesetError::EmptyAnimation);
+    }
+
+    let mut rules = [None; MASK_COUNT];
+    for rule in definition.rules.iter() {
+      let slot = &mut rules[rule.mask()];
+      if slot.is_some() {
+        return Err(TilesetError::DuplicateMask([rule.right, rule.back, rule.left]));
+      }
+      *slot = Some((rule.index, rule.rotation));
+    }
+
+    let missing: Vec<_> = (0..MASK_COUNT).filter(|mask| rules[*mask].is_none()).map(mask_inputs).collect();
+    if !missing.is_empty() {
+      return Err(TilesetError::MissingMasks(missing));
+    }
+    Ok(AutotileRules {
+      texture: definition.texture,
+      animation: definition.animation,
+      rules: rules.map(Option::unwrap),
+    })
+  }
+
+  pub fn parse(contents: &str) -> Result<AutotileRules, TilesetError> {
+    AutotileRules::new(serde_json::from_str(contents).map_err(TilesetError::Format)?)
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> Result<AutotileRules, TilesetError> {
+    AutotileRules::parse(&std::fs::read_to_string(path).map_err(TilesetError::Io)?)
+  }
+
+  // Sizes the animation's stride from the number of frames in the loaded atlas.
+  // `is_multiple_of` would need Rust 1.87.
+  #[allow(clippy::manual_is_multiple_of)]
+  pub fn size_animation(&mut self, atlas_frames: u32) -> Result<(), TilesetError> {
+    let animation_frames = self.animation.frames;
+    if atlas_frames == 0 || atlas_frames % animation_frames != 0 {
+      return Err(TilesetError::UnevenAtlas { atlas_frames, animation_frames });
+    }
+    self.animation.stride = atlas_frames / animation_frames;
+    Ok(())
+  }
+
+  // Index of an animation frame of the tile whose first frame is `base`.
+  pub fn animated_index(&self, base: u32, frame: u32) -> u32 {
+    base + (frame % self.animation.frames) * self.animation.stride
+  }
+
+  // The frame and flip for a conveyor facing `direction` with the given sides, in the
+  // order of `ConveyorDirection::neighbors_to_check_for_connections`.
+  pub fn texture(&self, direction: ConveyorDirection, neighbors: &[ConveyorNeighbor; 3]) -> (TileTextureIndex, TileFlip) {
+    let inputs = neighbors.map(|neighbor| neighbor == ConveyorNeighbor::Input);
+    let (index, rotation) = self.rules[mask_index(inputs)];
+    match rotation {
+      RuleRotation::Frames => {
+        let offset = match direction {
+          ConveyorDirection::North => 0,
+          ConveyorDirection::East => 1,
+          ConveyorDirection::South => 2,
+          ConveyorDirection::West => 3,
+        };
+        (TileTextureIndex(index + offset), TileFlip::default())
+      }
+      RuleRotation::Flip => {
+        let flip = match direction {
+          ConveyorDirection::North => TileFlip::default(),
+          ConveyorDirection::East => TileFlip { x: true, y: false, d: true },
+          ConveyorDirection::South => TileFlip { x: true, y: true, d: false },
+          ConveyorDirection::West => TileFlip { x: false, y: true, d: true },
+        };
+        (TileTextureIndex(index), flip)
+      }
+    }
+  }
+}
+
+// Replaces the built-in rules with the tileset definition in the assets folder, so belt
+// tilesets can be changed without rebuilding the game. The file is read directly rather
+// than through the AssetServer because the tilemaps are spawned with these rules in the
+// same startup set, before an asset load could finish.
+pub fn load_autotile_rules(mut rules: ResMut<AutotileRules>) {
+  let path = FileAssetIo::get_base_path().join("assets").join(TILESET_PATH);
+  match AutotileRules::load(&path) {
+    Ok(loaded) => *rules = loaded,
+    Err(error) => error!("Could not load the tileset definition {}, using the built-in one: {}", path.display(), error),
+  }
+}
+
+#[cfg(test)]
+mod autotile_test {
+  use super::*;
+
+  fn rule(right: bool, back: bool, left: bool, index: u32) -> AutotileRule {
+    AutotileRule { right, back, left, index, rotation: RuleRotation::Frames }
+  }
+
+  #[test]
+  fn built_in_rules_cover_every_mask() {
+    let definition: TilesetDefinition = serde_json::from_str(BUILT_IN_TILESET).unwrap();
+    let rules = AutotileRules::default();
+    for mask in 0..MASK_COUNT {
+      let inputs = mask_inputs(mask);
+      let rule = definition.rules.iter().find(|rule| rule.mask() == mask).expect("every mask should have a rule");
+      let neighbors = inputs.map(|input| match input {
+        true => ConveyorNeighbor::Input,
+        false => ConveyorNeighbor::None,
+      });
+      let (index, flip) = rules.texture(ConveyorDirection::North, &neighbors);
+      assert_eq!(index.0, rule.index, "inputs (right, back, left) {:?}", inputs);
+      assert!(!flip.x && !flip.y && !flip.d);
+    }
+  }
+
+  #[test]
+  fn missing_and_duplicate_masks() {
+    let definition = TilesetDefinition {
+      texture: "conveyor.png".to_string(),
+      rules: vec![rule(false, false, false, 1), rule(true, true, true, 17)],
+      animation: AnimationDefinition::default(),
+    };
+    let Err(TilesetError::MissingMasks(missing)) = AutotileRules::new(definition) else { panic!("expected missing masks") };
+    assert_eq!(missing.len(), MASK_COUNT - 2);
+    assert!(!missing.contains(&[true, true, true]));
+
+    let definition = TilesetDefinition {
+      texture: "conveyor.png".to_string(),
+      rules: vec![rule(true, false, false, 1), rule(true, false, false, 9)],
+      animation: AnimationDefinition::default(),
+    };
+    assert!(matches!(AutotileRules::new(definition), Err(TilesetError::DuplicateMask([true, false, false]))));
+  }
+
+  #[test]
+  fn animation_frames() {
+    let animation = AnimationDefinition { frames: 4, stride: 29, ticks_per_frame: 2 };
+    assert_eq!(animation.frame_at(0, 1), 0);
+    assert_eq!(animation.frame_at(3, 1), 1);
+    assert_eq!(animation.frame_at(8, 1), 0);
+    // faster belts scroll through their frames sooner
+    assert_eq!(animation.frame_at(3, 2), 3);
+    assert_eq!(animation.frame_at(4, 3), 2);
+
+    let rules = AutotileRules { animation, ..AutotileRules::default() };
+    assert_eq!(rules.animated_index(13, 0), 13);
+    assert_eq!(rules.animated_index(13, 3), 13 + 3 * 29);
+  }
+
+  #[test]
+  fn stride_is_sized_from_the_atlas() {
+    let mut rules = AutotileRules::default();
+    assert_eq!(rules.animated_index(13, 1), 13);
+
+    let atlas = include_bytes!("../../assets/conveyor.aseprite");
+    let atlas_frames = u16::from_le_bytes([atlas[6], atlas[7]]) as u32;
+    rules.size_animation(atlas_frames).unwrap();
+    // every frame of the last copy is still within the atlas
+    assert!(rules.animated_index(28, rules.animation.frames - 1) < atlas_frames);
+    assert_eq!(rules.animated_index(1, 1), 1 + atlas_frames / rules.animation.frames);
+
+    assert!(matches!(
+      rules.size_animation(atlas_frames + 1),
+      Err(TilesetError::UnevenAtlas { animation_frames: 4, .. })
+    ));
+  }
+}
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 use bevy_ecs_tilemap::prelude::*;
 
+use crate::settings::prelude::*;
+
 use super::{PlayfieldSize};
 
 pub mod plugin_exports {
@@ -82,6 +84,30 @@ pub fn place_background_tiles(
   }
 }
 
+// Tint of the floor tiles. The high contrast palette darkens them so belts stand out.
+pub fn background_color(palette: ColorPalette) -> Color {
+  match palette {
+    ColorPalette::HighContrast => Color::rgb(0.3, 0.3, 0.3),
+    _ => Color::WHITE,
+  }
+}
+
+pub fn apply_background_palette(
+  settings: Option<Res<Settings>>,
+  background_layer: Query<&TileStorage, With<BackgroundTileLayer>>,
+  mut tile_colors: Query<&mut TileColor>,
+) {
+  let Some(settings) = settings.filter(|settings| settings.is_changed()) else { return; };
+  let Ok(background_storage) = background_layer.get_single() else { return; };
+
+  let color = TileColor(background_color(settings.palette));
+  for entity in background_storage.iter().flatten() {
+    if let Ok(mut tile_color) = tile_colors.get_mut(*entity) {
+      *tile_color = color;
+    }
+  }
+}
+
 pub fn insert_background_texture(
   mut commands: Commands,
   asset_server: Res<AssetServer>,
@@ -95,5 +121,5 @@ pub fn insert_background_texture(
     return; 
   };
 
-  commands.entity(background_entity).insert(TilemapTexture::Single(asset_server.load("background.png")));
+  commands.entity(background_entity).insert(TilemapTexture::Single(asset_server.load("background.aseprite#image")));
 }
\ No newline at end of file
@@ -0,0 +1,99 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::vec2_traits::{AsIVec2, TilePosFromSigned};
+
+use super::ConveyorDirection;
+
+// Where a conveyor hands its packages off to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ConveyorOutput {
+  Connected(TilePos),
+  // The conveyor points past the edge of the playfield.
+  OffPlayfield,
+  // The conveyor points at a tile with nothing on it.
+  Empty(TilePos),
+  // The conveyor points at another conveyor which points straight back at it.
+  HeadOn(TilePos),
+}
+
+impl ConveyorOutput {
+  pub fn is_dead_end(&self) -> bool {
+    !matches!(self, ConveyorOutput::Connected(_))
+  }
+}
+
+pub fn tile_positions(size: &TilemapSize) -> impl Iterator<Item = TilePos> {
+  let size = *size;
+  (0..size.y).flat_map(move |y| (0..size.x).map(move |x| TilePos { x, y }))
+}
+
+pub fn conveyor_direction_at(
+  tile_storage: &TileStorage,
+  directions: &impl Fn(Entity) -> Option<ConveyorDirection>,
+  pos: &TilePos,
+) -> Option<ConveyorDirection> {
+  tile_storage.checked_get(pos).and_then(directions)
+}
+
+pub fn conveyor_output(
+  tile_storage: &TileStorage,
+  directions: &impl Fn(Entity) -> Option<ConveyorDirection>,
+  pos: &TilePos,
+) -> Option<ConveyorOutput> {
+  let direction = conveyor_direction_at(tile_storage, directions, pos)?;
+  let Ok(target) = (pos.as_ivec2() + direction.offset()).to_tile_pos(&tile_storage.size) else {
+    return Some(ConveyorOutput::OffPlayfield);
+  };
+  let output = match conveyor_direction_at(tile_storage, directions, &target) {
+    None => ConveyorOutput::Empty(target),
+    Some(target_direction) if target_direction == direction.opposite() => ConveyorOutput::HeadOn(target),
+    Some(_) => ConveyorOutput::Connected(target),
+  };
+  Some(output)
+}
+
+// Every closed cycle of conveyors feeding each other, in the order packages would travel.
+pub fn find_cycles(
+  tile_storage: &TileStorage,
+  directions: &impl Fn(Entity) -> Option<ConveyorDirection>,
+) -> Vec<Vec<TilePos>> {
+  // every conveyor has at most one output, so following the outputs from any
+  // conveyor either ends or walks into a cycle
+  let mut visited = HashSet::new();
+  let mut cycles = Vec::new();
+
+  for start in tile_positions(&tile_storage.size) {
+    if visited.contains(&start) || conveyor_direction_at(tile_storage, directions, &start).is_none() {
+      continue;
+    }
+
+    let mut path = Vec::new();
+    let mut current = start;
+    loop {
+      if visited.contains(&current) {
+        if let Some(cycle_start) = path.iter().position(|pos| *pos == current) {
+          cycles.push(path.split_off(cycle_start));
+        }
+        break;
+      }
+      visited.insert(current);
+      path.push(current);
+
+      match conveyor_output(tile_storage, directions, &current) {
+        Some(ConveyorOutput::Connected(next)) => current = next,
+        _ => break,
+      }
+    }
+  }
+
+  cycles
+}
+
+// Every conveyor which is part of a closed cycle of conveyors feeding each other.
+pub fn find_loops(
+  tile_storage: &TileStorage,
+  directions: &impl Fn(Entity) -> Option<ConveyorDirection>,
+) -> HashSet<TilePos> {
+  find_cycles(tile_storage, directions).into_iter().flatten().collect()
+}
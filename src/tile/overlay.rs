@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{delivery::prelude::*, settings::prelude::*, stats::prelude::*};
+
+use super::{network::*, ConveyorDirection, ConveyorTileLayer, TileType};
+
+pub mod prelude {
+  pub use super::toggle_conveyor_overlay;
+  pub use super::HighlightedTiles;
+}
+
+pub mod plugin_exports {
+  pub use super::highlight_tiles;
+  pub use super::update_conveyor_overlay;
+  pub use super::ConveyorOverlay;
+  pub use super::HighlightedTiles;
+}
+
+// Utilisation above which a conveyor counts as backed up.
+pub const BACKED_UP_UTILISATION: f32 = 0.9;
+
+#[derive(Debug, Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct ConveyorOverlay {
+  pub enabled: bool,
+}
+
+// Tiles which should stand out from the rest, for example because a problem was found with them.
+#[derive(Debug, Resource, Default)]
+pub struct HighlightedTiles {
+  pub tiles: Vec<TilePos>,
+  previous: Vec<TilePos>,
+}
+
+// Stands apart from every conveyor state of the palette, so highlights still show up
+// with the overlay on.
+pub fn highlight_color(palette: ColorPalette) -> Color {
+  match palette {
+    ColorPalette::Standard => Color::rgb(1.0, 1.0, 0.2),
+    ColorPalette::RedGreen => Color::rgb(1.0, 0.3, 1.0),
+    ColorPalette::BlueYellow => Color::rgb(0.3, 1.0, 0.3),
+    ColorPalette::HighContrast => Color::rgb(1.0, 0.5, 0.0),
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ConveyorState {
+  Flowing,
+  BackedUp,
+  Starved,
+  DeadEnd,
+  Loop,
+}
+
+impl ConveyorState {
+  pub const VALUES: [ConveyorState; 5] =
+    [ConveyorState::Flowing, ConveyorState::BackedUp, ConveyorState::Starved, ConveyorState::DeadEnd, ConveyorState::Loop];
+
+  pub fn color(&self, palette: ColorPalette) -> Color {
+    match (palette, self) {
+      (ColorPalette::Standard, ConveyorState::Flowing) => Color::rgb(0.45, 1.0, 0.45),
+      (ColorPalette::Standard, ConveyorState::BackedUp) => Color::rgb(1.0, 0.65, 0.2),
+      (ColorPalette::Standard, ConveyorState::Starved) => Color::rgb(0.5, 0.6, 1.0),
+      (ColorPalette::Standard, ConveyorState::DeadEnd) => Color::rgb(1.0, 0.3, 0.3),
+      (ColorPalette::Standard, ConveyorState::Loop) => Color::rgb(0.85, 0.4, 1.0),
+      // without red and green, flowing and dead end belts differ in brightness as well as hue
+      (ColorPalette::RedGreen, ConveyorState::Flowing) => Color::rgb(0.35, 0.7, 1.0),
+      (ColorPalette::RedGreen, ConveyorState::BackedUp) => Color::rgb(1.0, 0.85, 0.3),
+      (ColorPalette::RedGreen, ConveyorState::Starved) => Color::rgb(0.7, 0.7, 0.7),
+      (ColorPalette::RedGreen, ConveyorState::DeadEnd) => Color::rgb(0.8, 0.4, 0.0),
+      (ColorPalette::RedGreen, ConveyorState::Loop) => Color::rgb(0.8, 0.6, 0.7),
+      (ColorPalette::BlueYellow, ConveyorState::Flowing) => Color::rgb(0.3, 0.9, 0.9),
+      (ColorPalette::BlueYellow, ConveyorState::BackedUp) => Color::rgb(1.0, 0.6, 0.75),
+      (ColorPalette::BlueYellow, ConveyorState::Starved) => Color::rgb(0.7, 0.7, 0.7),
+      (ColorPalette::BlueYellow, ConveyorState::DeadEnd) => Color::rgb(0.9, 0.1, 0.1),
+      (ColorPalette::BlueYellow, ConveyorState::Loop) => Color::rgb(0.3, 0.3, 0.3),
+      (ColorPalette::HighContrast, ConveyorState::Flowing) => Color::rgb(0.0, 1.0, 0.0),
+      (ColorPalette::HighContrast, ConveyorState::BackedUp) => Color::rgb(1.0, 1.0, 0.0),
+      (ColorPalette::HighContrast, ConveyorState::Starved) => Color::rgb(0.0, 0.8, 1.0),
+      (ColorPalette::HighContrast, ConveyorState::DeadEnd) => Color::rgb(1.0, 0.0, 0.0),
+      (ColorPalette::HighContrast, ConveyorState::Loop) => Color::rgb(1.0, 0.0, 1.0),
+    }
+  }
+
+  fn from_utilisation(utilisation: Option<f32>) -> ConveyorState {
+    match utilisation {
+      Some(utilisation) if utilisation >= BACKED_UP_UTILISATION => ConveyorState::BackedUp,
+      Some(utilisation) if utilisation > 0.0 => ConveyorState::Flowing,
+      _ => ConveyorState::Starved,
+    }
+  }
+}
+
+pub fn toggle_conveyor_overlay(
+  keyboard_input: Res<Input<KeyCode>>,
+  mut overlay: ResMut<ConveyorOverlay>,
+) {
+  if keyboard_input.just_pressed(KeyCode::F2) {
+    overlay.enabled = !overlay.enabled;
+  }
+}
+
+pub fn update_conveyor_overlay(
+  overlay: Res<ConveyorOverlay>,
+  settings: Option<Res<Settings>>,
+  stats: Option<Res<ThroughputStats>>,
+  tilemap: Query<(&TileStorage, &ConveyorTileLayer)>,
+  customer_layer: Query<&TileStorage, With<CustomerTileLayer>>,
+  conveyor_directions: Query<&ConveyorDirection>,
+  mut tile_colors: Query<(&mut TileColor, &TileType)>,
+) {
+  if !overlay.enabled {
+    if overlay.is_changed() {
+      for (mut tile_color, tile_type) in tile_colors.iter_mut() {
+        *tile_color = TileColor(tile_type.tint());
+      }
+    }
+    return;
+  }
+
+  let Ok((tile_storage, _)) = tilemap.get_single() else {
+    error!(
+      "Tilemap query for the conveyor layer returned {} items when it only should have returned 1.",
+      tilemap.iter().len(),
+    );
+    return;
+  };
+
+  let palette = settings.map_or(ColorPalette::Standard, |settings| settings.palette);
+  let directions = |entity: Entity| conveyor_directions.get(entity).ok().copied();
+  let loops = find_loops(tile_storage, &directions);
+  // conveyors pointing into a customer hand their packages off rather than dead-ending
+  let is_dead_end = |output: ConveyorOutput| match output {
+    ConveyorOutput::Empty(target) => customer_layer
+      .get_single()
+      .map_or(true, |customer_storage| customer_storage.get(&target).is_none()),
+    output => output.is_dead_end(),
+  };
+  // judge flow on the last finished window so the colours don't flicker when a new window starts
+  let window = stats
+    .as_ref()
+    .map(|stats| stats.history().next_back().unwrap_or(stats.current()));
+
+  for pos in tile_positions(&tile_storage.size) {
+    let Some(entity) = tile_storage.get(&pos) else { continue; };
+    let Ok((mut tile_color, _)) = tile_colors.get_mut(entity) else { continue; };
+
+    let state = if loops.contains(&pos) {
+      ConveyorState::Loop
+    } else if conveyor_output(tile_storage, &directions, &pos).is_some_and(is_dead_end) {
+      ConveyorState::DeadEnd
+    } else {
+      ConveyorState::from_utilisation(
+        window.and_then(|window| window.conveyors.get(&pos)).map(|samples| samples.utilisation()),
+      )
+    };
+
+    *tile_color = TileColor(state.color(palette));
+  }
+}
+
+pub fn highlight_tiles(
+  overlay: Res<ConveyorOverlay>,
+  settings: Option<Res<Settings>>,
+  mut highlighted: ResMut<HighlightedTiles>,
+  tilemap: Query<(&TileStorage, &ConveyorTileLayer)>,
+  mut tile_colors: Query<(&mut TileColor, &TileType)>,
+) {
+  let Ok((tile_storage, _)) = tilemap.get_single() else { return; };
+
+  // the overlay recolours every conveyor each frame, so tiles only need resetting without it
+  if !overlay.enabled {
+    for pos in highlighted.previous.iter().filter(|pos| !highlighted.tiles.contains(pos)) {
+      let Some(entity) = tile_storage.checked_get(pos) else { continue; };
+      if let Ok((mut tile_color, tile_type)) = tile_colors.get_mut(entity) {
+        *tile_color = TileColor(tile_type.tint());
+      }
+    }
+  }
+
+  let color = highlight_color(settings.map_or(ColorPalette::Standard, |settings| settings.palette));
+  for pos in highlighted.tiles.iter() {
+    let Some(entity) = tile_storage.checked_get(pos) else { continue; };
+    if let Ok((mut tile_color, _)) = tile_colors.get_mut(entity) {
+      *tile_color = TileColor(color);
+    }
+  }
+
+  highlighted.previous = highlighted.tiles.clone();
+}
+
+#[cfg(test)]
+mod overlay_test {
+  use super::*;
+
+  fn rgb(color: Color) -> Vec3 {
+    Vec3::from_slice(&color.as_rgba_f32()[..3])
+  }
+
+  #[test]
+  fn highlights_stand_out_from_conveyor_states() {
+    for palette in ColorPalette::VALUES {
+      let highlight = rgb(highlight_color(palette));
+      for state in ConveyorState::VALUES {
+        let distance = highlight.distance(rgb(state.color(palette)));
+        assert!(distance >= 0.3, "{palette:?} highlight is too close to {state:?} ({distance})");
+      }
+    }
+  }
+}
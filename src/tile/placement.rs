@@ -1,47 +1,89 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_ecs_tilemap::{
   prelude::{TilemapId, TilemapSize},
-  tiles::{TileBundle, TilePos, TileStorage, TileTextureIndex},
+  tiles::{TileBundle, TileColor, TilePos, TileStorage, TileTextureIndex},
 };
 
-use crate::{input::chained_tile::ChainedTilePlaceDirection, vec2_traits::*};
+use crate::{input::{chained_tile::ChainedTilePlaceDirection, prelude::*}, vec2_traits::*};
 
-use super::prelude::*;
+use super::{animation::AutotileBase, prelude::*};
 
 pub mod plugin_exports {
+  pub use super::continue_chain;
   pub use super::place_tile;
+  pub use super::ChainedPlacement;
+  pub use super::update_tile_direction;
+  pub use super::PlacementRejected;
   pub use super::PreviousPlaceAttempt;
+  pub use super::TileChain;
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionReason {
+  CannotAfford { cost: i64, balance: i64 },
+  OutOfTiles(TileType),
+}
+
+// Sent when the level's budget doesn't allow a tile the player tries to build.
+#[derive(Debug, Clone)]
+pub struct PlacementRejected {
+  pub pos: TilePos,
+  pub reason: RejectionReason,
+}
+
+// Puts a tile at `position`. A tile which is already there is turned into the new one
+// rather than replaced, keeping its entity and any components the new type has in common
+// with it.
 pub fn spawn_tile(
   commands: &mut Commands,
   position: TilePos,
+  tile_type: TileType,
   tile_storage: &mut TileStorage,
   tilemap_entity: Entity,
   direction: ConveyorDirection,
   placed_tiles: &mut EventWriter<UpdatedTile>,
 ) -> Entity {
-  let tile_entity = commands
-    .spawn(TileBundle {
-      position,
-      tilemap_id: TilemapId(tilemap_entity),
-      texture_index: TileTextureIndex(direction.texture_index()),
-      ..Default::default()
-    })
-    .insert(direction)
-    .id();
-  tile_storage.set(&position, tile_entity);
+  let texture_index = match tile_type {
+    TileType::Conveyor | TileType::FastConveyor | TileType::ExpressConveyor => direction.texture_index(),
+  };
+  let tile_entity = match tile_storage.get(&position) {
+    Some(tile_entity) => {
+      commands.entity(tile_entity).insert((
+        tile_type,
+        direction,
+        TileTextureIndex(texture_index),
+        TileColor(tile_type.tint()),
+        AutotileBase(texture_index),
+      ));
+      tile_entity
+    }
+    None => {
+      let tile_entity = commands
+        .spawn(TileBundle {
+          position,
+          tilemap_id: TilemapId(tilemap_entity),
+          texture_index: TileTextureIndex(texture_index),
+          color: TileColor(tile_type.tint()),
+          ..Default::default()
+        })
+        .insert((tile_type, direction, AutotileBase(texture_index)))
+        .id();
+      tile_storage.set(&position, tile_entity);
+      tile_entity
+    }
+  };
   placed_tiles.send(UpdatedTile { pos: position });
   tile_entity
 }
 
 #[derive(Debug, Resource, Clone, Reflect, Default)]
+#[reflect(Resource)]
 pub struct PreviousPlaceAttempt {
   pub position: IVec2,
   pub direction: ConveyorDirection,
 }
 
-fn update_tile_direction(
+pub fn update_tile_direction(
   commands: &mut Commands,
   position: TilePos,
   tile_storage: &TileStorage,
@@ -53,53 +95,83 @@ fn update_tile_direction(
   placed_tiles.send(UpdatedTile { pos: position });
 }
 
-pub fn place_tile(
-  mut commands: &mut Commands,
-  new_tile_position: IVec2,
-  tile_storage: &mut TileStorage,
-  tilemap_entity: Entity,
+// A tile put down as part of a stroke, which may carry on from the tile before it.
+#[derive(Debug, Clone, Copy)]
+pub struct ChainedPlacement {
+  pub position: IVec2,
+  pub tile_type: TileType,
+  pub place_direction: ChainedTilePlaceDirection,
+  pub chain_with_previous_tile: bool,
+}
+
+// How far the current stroke of chained tiles got, and which way its tiles face.
+#[derive(SystemParam)]
+pub struct TileChain<'w> {
+  previous_place_attempt: ResMut<'w, PreviousPlaceAttempt>,
+  selected_tile_direction: ResMut<'w, SelectedTileDirection>,
+}
+
+// Carries a stroke of chained tiles on to the placement's position, turning the previous
+// tile to face it and picking the direction for the new one. Tiles the budget refuses go
+// through this too, so the rest of the stroke still lines up with them.
+pub fn continue_chain(
+  commands: &mut Commands,
+  placement: &ChainedPlacement,
+  tile_storage: &TileStorage,
   tilemap_size: &TilemapSize,
-  previous_place_attempt: &mut PreviousPlaceAttempt,
-  mut placed_tiles: &mut EventWriter<UpdatedTile>,
-  place_direction: ChainedTilePlaceDirection,
-  selected_tile_direction: &mut ConveyorDirection,
-  chain_with_previous_tile: bool,
+  chain: &mut TileChain,
+  placed_tiles: &mut EventWriter<UpdatedTile>,
 ) {
-  if chain_with_previous_tile {
-    let offset = new_tile_position - previous_place_attempt.position;
+  let place_direction = placement.place_direction;
+  if placement.chain_with_previous_tile {
+    let offset = placement.position - chain.previous_place_attempt.position;
     if let Some(direction_moved) = ConveyorDirection::from_ivec2(offset) {
-      let changed_direction = direction_moved != previous_place_attempt.direction;
+      let changed_direction = direction_moved != chain.previous_place_attempt.direction;
       let reversed = place_direction == ChainedTilePlaceDirection::Revesed;
-      let opposite_direction_as_previous = direction_moved == previous_place_attempt.direction;
+      let opposite_direction_as_previous = direction_moved == chain.previous_place_attempt.direction;
 
       if (changed_direction && !reversed) || (opposite_direction_as_previous && reversed) {
-        let previous_tile_position = previous_place_attempt.position.to_tile_pos(&tilemap_size);
+        let previous_tile_position = chain.previous_place_attempt.position.to_tile_pos(tilemap_size);
         if let Ok(previous_tile_position) = previous_tile_position {
           update_tile_direction(
-            &mut commands,
+            commands,
             previous_tile_position,
-            &tile_storage,
+            tile_storage,
             direction_moved.apply_place_direction(place_direction),
-            &mut placed_tiles,
+            placed_tiles,
           );
         }
       }
-      *selected_tile_direction = direction_moved.apply_place_direction(place_direction);
+      chain.selected_tile_direction.direction = direction_moved.apply_place_direction(place_direction);
     }
   }
 
-  if let Ok(new_tile_position) = new_tile_position.to_tile_pos(&tilemap_size) {
+  *chain.previous_place_attempt = PreviousPlaceAttempt {
+    position: placement.position,
+    direction: chain.selected_tile_direction.direction,
+  };
+}
+
+pub fn place_tile(
+  commands: &mut Commands,
+  placement: &ChainedPlacement,
+  tile_storage: &mut TileStorage,
+  tilemap_entity: Entity,
+  tilemap_size: &TilemapSize,
+  chain: &mut TileChain,
+  placed_tiles: &mut EventWriter<UpdatedTile>,
+) {
+  continue_chain(commands, placement, tile_storage, tilemap_size, chain, placed_tiles);
+
+  if let Ok(new_tile_position) = placement.position.to_tile_pos(tilemap_size) {
     spawn_tile(
       commands,
       new_tile_position,
+      placement.tile_type,
       tile_storage,
       tilemap_entity,
-      *selected_tile_direction,
-      &mut placed_tiles,
+      chain.selected_tile_direction.direction,
+      placed_tiles,
     );
   }
-  *previous_place_attempt = PreviousPlaceAttempt {
-    position: new_tile_position,
-    direction: *selected_tile_direction,
-  };
 }
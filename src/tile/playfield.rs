@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 use bevy_ecs_tilemap::prelude::*;
 
+use super::autotile::AutotileRules;
+
 pub mod plugin_exports {
   pub use super::*;
 }
@@ -10,9 +12,29 @@ pub mod prelude {
   pub use super::ConveyorTileLayer;
 }
 
+// Larger playfields take ages to spawn their background tiles and don't fit in memory
+// for long.
+pub const MAX_PLAYFIELD_SIDE: u32 = 1024;
+
 #[derive(Debug, Resource, Clone)]
 pub struct PlayfieldSize(pub UVec2);
 
+impl PlayfieldSize {
+  // Checks a size given on the command line or read from a save file.
+  pub fn new(size: UVec2) -> Result<PlayfieldSize, String> {
+    if size.x == 0 || size.y == 0 {
+      return Err(format!("a {}x{} playfield has no area", size.x, size.y));
+    }
+    if size.x > MAX_PLAYFIELD_SIDE || size.y > MAX_PLAYFIELD_SIDE {
+      return Err(format!(
+        "a {}x{} playfield is larger than {}x{}",
+        size.x, size.y, MAX_PLAYFIELD_SIDE, MAX_PLAYFIELD_SIDE,
+      ));
+    }
+    Ok(PlayfieldSize(size))
+  }
+}
+
 #[derive(Debug, Component)]
 pub struct ConveyorTileLayer;
 
@@ -47,6 +69,7 @@ pub fn setup_playfield(
 pub fn insert_playfield_texture(
   mut commands: Commands,
   asset_server: Res<AssetServer>,
+  autotile_rules: Res<AutotileRules>,
   background_layer: Query<(Entity, &ConveyorTileLayer)>,
 ) {
   let Ok((background_entity, _)) = background_layer.get_single() else {
@@ -57,5 +80,5 @@ pub fn insert_playfield_texture(
     return; 
   };
 
-  commands.entity(background_entity).insert(TilemapTexture::Single(asset_server.load("conveyor.png")));
+  commands.entity(background_entity).insert(TilemapTexture::Single(asset_server.load(autotile_rules.texture.as_str())));
 }
@@ -0,0 +1,194 @@
+use std::path::Path;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_tilemap::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+  network::tile_positions, placement::spawn_tile, validation::{validate_layout, LayoutReport}, ConveyorDirection,
+  ConveyorTileLayer, PlayfieldSize, TileType, UpdatedTile,
+};
+
+pub mod prelude {
+  pub use super::SavedLayout;
+}
+
+pub mod plugin_exports {
+  pub use super::place_saved_layout;
+}
+
+pub const QUICKSAVE_PATH: &str = "layout.json";
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct SavedTile {
+  pub x: u32,
+  pub y: u32,
+  pub direction: ConveyorDirection,
+  // Saves from before there were faster belts only hold plain conveyors.
+  #[serde(default)]
+  pub tile_type: TileType,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct SavedCustomer {
+  pub x: u32,
+  pub y: u32,
+  #[serde(default)]
+  pub first_order_tick: u64,
+}
+
+// A player's conveyor layout, as written to and read from save files.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Resource, Serialize, Deserialize)]
+pub struct SavedLayout {
+  pub playfield_size: UVec2,
+  pub tiles: Vec<SavedTile>,
+  #[serde(default)]
+  pub customers: Vec<SavedCustomer>,
+  // Money the player starts the level with.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub starting_money: Option<i64>,
+  // Number of each tile type the player may build, for puzzle levels. Tile types left
+  // out can't be built at all.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub inventory: Option<HashMap<TileType, u32>>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+  Io(std::io::Error),
+  Format(serde_json::Error),
+  PlayfieldSize(String),
+}
+
+impl std::fmt::Display for SaveError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SaveError::Io(error) => write!(f, "{}", error),
+      SaveError::Format(error) => write!(f, "invalid save file: {}", error),
+      SaveError::PlayfieldSize(error) => write!(f, "invalid save file: {}", error),
+    }
+  }
+}
+
+impl SavedLayout {
+  pub fn from_tiles(
+    tile_storage: &TileStorage,
+    conveyors: &impl Fn(Entity) -> Option<(ConveyorDirection, TileType)>,
+  ) -> SavedLayout {
+    let tiles = tile_positions(&tile_storage.size)
+      .filter_map(|pos| {
+        let (direction, tile_type) = tile_storage.get(&pos).and_then(conveyors)?;
+        Some(SavedTile { x: pos.x, y: pos.y, direction, tile_type })
+      })
+      .collect();
+    SavedLayout {
+      playfield_size: UVec2::new(tile_storage.size.x, tile_storage.size.y),
+      tiles,
+      customers: Vec::new(),
+      starting_money: None,
+      inventory: None,
+    }
+  }
+
+  pub fn tilemap_size(&self) -> TilemapSize {
+    TilemapSize { x: self.playfield_size.x, y: self.playfield_size.y }
+  }
+
+  pub fn tiles_outside_playfield(&self) -> impl Iterator<Item = &SavedTile> {
+    let size = self.tilemap_size();
+    self.tiles.iter().filter(move |tile| !TilePos { x: tile.x, y: tile.y }.within_map_bounds(&size))
+  }
+
+  // Checks the layout for problems without spawning it into a world.
+  pub fn validate(&self) -> LayoutReport {
+    let mut tile_storage = TileStorage::empty(self.tilemap_size());
+    let mut directions = HashMap::new();
+    for (index, tile) in self.tiles.iter().enumerate() {
+      let entity = Entity::from_raw(index as u32);
+      tile_storage.checked_set(&TilePos { x: tile.x, y: tile.y }, entity);
+      directions.insert(entity, tile.direction);
+    }
+    validate_layout(&tile_storage, &|entity| directions.get(&entity).copied())
+  }
+
+  // Rejects playfields with the same bounds as `--playfield`, so a save file can't ask
+  // for a tilemap that doesn't fit in memory.
+  pub fn parse(contents: &str) -> Result<SavedLayout, SaveError> {
+    let layout: SavedLayout = serde_json::from_str(contents).map_err(SaveError::Format)?;
+    PlayfieldSize::new(layout.playfield_size).map_err(SaveError::PlayfieldSize)?;
+    Ok(layout)
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> Result<SavedLayout, SaveError> {
+    SavedLayout::parse(&std::fs::read_to_string(path).map_err(SaveError::Io)?)
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+    let contents = serde_json::to_string_pretty(self).map_err(SaveError::Format)?;
+    std::fs::write(path, contents).map_err(SaveError::Io)
+  }
+}
+
+// Spawns the tiles of the `SavedLayout` resource, if there is one, into the conveyor layer.
+pub fn place_saved_layout(
+  mut commands: Commands,
+  saved_layout: Option<Res<SavedLayout>>,
+  mut tilemap: Query<(Entity, &mut TileStorage, &ConveyorTileLayer)>,
+  mut placed_tiles: EventWriter<UpdatedTile>,
+) {
+  let Some(saved_layout) = saved_layout else { return; };
+  let Ok((tilemap_entity, mut tile_storage, _)) = tilemap.get_single_mut() else {
+    error!(
+      "Tilemap query for the conveyor layer returned {} items when it only should have returned 1.",
+      tilemap.iter().len(),
+    );
+    return;
+  };
+
+  for tile in saved_layout.tiles.iter() {
+    let position = TilePos { x: tile.x, y: tile.y };
+    if !position.within_map_bounds(&tile_storage.size) {
+      warn!("Saved tile at ({}, {}) is outside of the playfield", tile.x, tile.y);
+      continue;
+    }
+    spawn_tile(&mut commands, position, tile.tile_type, &mut tile_storage, tilemap_entity, tile.direction, &mut placed_tiles);
+  }
+}
+
+pub fn save_layout(
+  keyboard_input: Res<Input<KeyCode>>,
+  loaded_layout: Option<Res<SavedLayout>>,
+  tilemap: Query<(&TileStorage, &ConveyorTileLayer)>,
+  conveyors: Query<(&ConveyorDirection, &TileType)>,
+) {
+  if !keyboard_input.just_pressed(KeyCode::F5) {
+    return;
+  }
+  let Ok((tile_storage, _)) = tilemap.get_single() else { return; };
+
+  let conveyors = |entity: Entity| conveyors.get(entity).ok().map(|(direction, tile_type)| (*direction, *tile_type));
+  let mut layout = SavedLayout::from_tiles(tile_storage, &conveyors);
+  // customers and budgets can't be changed by the player, so they're kept as they were loaded
+  if let Some(loaded_layout) = loaded_layout {
+    layout.customers = loaded_layout.customers.clone();
+    layout.starting_money = loaded_layout.starting_money;
+    layout.inventory = loaded_layout.inventory.clone();
+  }
+  match layout.save(QUICKSAVE_PATH) {
+    Ok(_) => info!("Saved layout to {}", QUICKSAVE_PATH),
+    Err(error) => error!("Could not save layout to {}: {}", QUICKSAVE_PATH, error),
+  }
+}
+
+#[cfg(test)]
+mod save_test {
+  use super::*;
+
+  #[test]
+  fn playfield_size_is_checked() {
+    let layout = |size: &str| format!(r#"{{ "playfield_size": {}, "tiles": [] }}"#, size);
+    assert!(SavedLayout::parse(&layout("[4, 4]")).is_ok());
+    assert!(matches!(SavedLayout::parse(&layout("[0, 0]")), Err(SaveError::PlayfieldSize(_))));
+    assert!(matches!(SavedLayout::parse(&layout("[4000000000, 4]")), Err(SaveError::PlayfieldSize(_))));
+  }
+}
@@ -3,59 +3,48 @@ use bevy_ecs_tilemap::prelude::*;
 
 use crate::vec2_traits::{AsIVec2, TilePosFromSigned};
 
-use super::{prelude::*, ConveyorTileLayer};
+use super::{animation::{AutotileBase, BeltAnimation}, autotile::AutotileRules, prelude::*, ConveyorTileLayer};
 
 pub mod systems {
   pub use super::conveyor_tile_update_graphics;
 }
 
-#[derive(Debug)]
-enum ConveyorNeighbor {
+// How a conveyor's side connects to the tile next to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConveyorNeighbor {
   Output,
   Input,
   None,
 }
 
-trait ToTileTextureIndex<T, U> {
-  fn get_tile_texture_index(&self, input: T) -> U;
-}
-
-mod ttti {
-  use super::ConveyorNeighbor::*;
-  use super::*;
-  impl ToTileTextureIndex<&[ConveyorNeighbor; 3], TileTextureIndex> for ConveyorDirection {
-    fn get_tile_texture_index(&self, input: &[ConveyorNeighbor; 3]) -> TileTextureIndex {
-      let base = match input {
-        [Input, Input, Input] => Some(17),
-        [Input, Input, None] => Some(25),
-        [Input, None, Input] => Some(13),
-        [Input, None, None] => Some(9),
-        [None, Input, Input] => Some(21),
-        [None, Input, None] => Some(1),
-        [None, None, Input] => Some(5),
-        [None, None, None] => Some(1),
-        _ => Option::None,
-      };
-
-      let Some(mut base) = base else {
-        return TileTextureIndex(0);
-      };
-
-      base += match self {
-        ConveyorDirection::North => 0,
-        ConveyorDirection::East => 1,
-        ConveyorDirection::South => 2,
-        ConveyorDirection::West => 3,
-      };
-      return TileTextureIndex(base);
+// Classifies the sides of the conveyor at `tile_pos` facing `direction`, in the order of
+// `ConveyorDirection::neighbors_to_check_for_connections`.
+pub fn conveyor_neighbors(
+  tile_store: &TileStorage,
+  directions: &impl Fn(Entity) -> Option<ConveyorDirection>,
+  tile_pos: &TilePos,
+  direction: ConveyorDirection,
+) -> [ConveyorNeighbor; 3] {
+  direction.neighbors_to_check_for_connections().map(|side| {
+    let Ok(tile_pos) = (side.offset() + tile_pos.as_ivec2()).to_tile_pos(&tile_store.size) else {
+      return ConveyorNeighbor::None;
+    };
+    let Some(neighbor_direction) = tile_store.get(&tile_pos).and_then(directions) else {
+      return ConveyorNeighbor::None;
+    };
+    match neighbor_direction == side.opposite() {
+      true => ConveyorNeighbor::Input,
+      false => ConveyorNeighbor::None,
     }
-  }
+  })
 }
 
 pub fn conveyor_tile_update_graphics(
   mut conveyor_tile_updates: EventReader<UpdatedTile>,
   tilemaps: Query<(&mut TileStorage, &TilemapSize, &ConveyorTileLayer)>,
-  mut tiles: Query<(Entity, &mut TileTextureIndex, &ConveyorDirection)>,
+  mut tiles: Query<(Entity, &mut TileTextureIndex, &mut TileFlip, &mut AutotileBase, &ConveyorDirection, &TileType)>,
+  autotile_rules: Res<AutotileRules>,
+  animation: Res<BeltAnimation>,
 ) {
   // get the position of all conveyors which need updating
   let conveyor_tile_updates: Vec<_> = conveyor_tile_updates.into_iter().collect();
@@ -90,37 +79,25 @@ pub fn conveyor_tile_update_graphics(
         let Some(tile_entity) = tile_store.get(tile_pos) else {
           return None;
         };
-        let Ok((_, _, conveyor_direction)) = tiles.get(tile_entity) else {
+        let Ok((_, _, _, _, conveyor_direction, _)) = tiles.get(tile_entity) else {
           return None;
         };
 
-        let side_states: [ConveyorNeighbor; 3] = conveyor_direction.neighbors_to_check_for_connections()
-        .iter()
-        .map(|direction| {
-          let Ok(tile_pos) = (direction.offset() + tile_pos.as_ivec2()).to_tile_pos(&tilemap_size) else {
-            return ConveyorNeighbor::None;
-          };
-          let Some(tile) = tile_store.get(&tile_pos) else {
-            return ConveyorNeighbor::None;
-          };
-          let Ok((_, _, neighbor_direction)) =  tiles.get(tile) else {
-            return ConveyorNeighbor::None;
-          };
-          match *neighbor_direction == direction.opposite() {
-            true => ConveyorNeighbor::Input,
-            false => ConveyorNeighbor::None,
-          }
-        }).collect::<Vec<ConveyorNeighbor>>().try_into().unwrap();
+        let directions = |entity: Entity| tiles.get(entity).ok().map(|(_, _, _, _, direction, _)| *direction);
+        let side_states = conveyor_neighbors(tile_store, &directions, tile_pos, *conveyor_direction);
 
-        Some((tile_entity, conveyor_direction.get_tile_texture_index(&side_states)))
+        Some((tile_entity, autotile_rules.texture(*conveyor_direction, &side_states)))
       }).collect();
 
     // apply each conveyor's texture
-    for (entity, texture) in texture_updates {
-      let Ok((_, mut tile_texture, _)) = tiles.get_mut(entity) else {
+    for (entity, (texture, flip)) in texture_updates {
+      let Ok((_, mut tile_texture, mut tile_flip, mut base, _, tile_type)) = tiles.get_mut(entity) else {
         continue;
       };
-      *tile_texture = texture;
+      let frame = autotile_rules.animation.frame_at(animation.tick, tile_type.belt_speed());
+      *base = AutotileBase(texture.0);
+      *tile_texture = TileTextureIndex(autotile_rules.animated_index(texture.0, frame));
+      *tile_flip = flip;
     }
   }
 }
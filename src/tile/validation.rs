@@ -0,0 +1,322 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{localisation::LocalisedName, vec2_traits::{AsIVec2, TilePosFromSigned}};
+
+use super::{network::*, ConveyorDirection, ConveyorTileLayer, UpdatedTile};
+
+pub mod prelude {
+  pub use super::LayoutProblem;
+  pub use super::LayoutValidation;
+}
+
+pub mod plugin_exports {
+  pub use super::update_layout_validation;
+  pub use super::LayoutValidation;
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LayoutProblemKind {
+  // A conveyor which points past the edge of the playfield.
+  OffPlayfield,
+  // A conveyor which points into the output of another conveyor, either head on or from
+  // the side of a line of conveyors.
+  IntoOutput,
+  // A closed cycle of conveyors feeding each other.
+  Loop,
+}
+
+impl LayoutProblemKind {
+  pub const VALUES: [LayoutProblemKind; 3] = [LayoutProblemKind::OffPlayfield, LayoutProblemKind::IntoOutput, LayoutProblemKind::Loop];
+
+  // In English, for the command line reports.
+  pub fn description(&self) -> &'static str {
+    match self {
+      LayoutProblemKind::OffPlayfield => "Conveyor points off the playfield",
+      LayoutProblemKind::IntoOutput => "Conveyor points into another conveyor's output",
+      LayoutProblemKind::Loop => "Conveyors form a closed loop",
+    }
+  }
+}
+
+impl LocalisedName for LayoutProblemKind {
+  fn name_key(&self) -> &'static str {
+    match self {
+      LayoutProblemKind::OffPlayfield => "problem.off_playfield",
+      LayoutProblemKind::IntoOutput => "problem.into_output",
+      LayoutProblemKind::Loop => "problem.loop",
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LayoutProblem {
+  pub kind: LayoutProblemKind,
+  pub tiles: Vec<TilePos>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct LayoutReport {
+  // Groups of conveyors which are linked to each other, in tile order.
+  pub components: Vec<Vec<TilePos>>,
+  pub problems: Vec<LayoutProblem>,
+}
+
+impl LayoutReport {
+  pub fn is_valid(&self) -> bool {
+    self.problems.is_empty()
+  }
+}
+
+fn find_root(parents: &mut HashMap<TilePos, TilePos>, pos: TilePos) -> TilePos {
+  let mut root = pos;
+  while parents[&root] != root {
+    root = parents[&root];
+  }
+  // flatten the path so later lookups are quick
+  let mut current = pos;
+  while current != root {
+    let next = parents[&current];
+    parents.insert(current, root);
+    current = next;
+  }
+  root
+}
+
+// Whether the conveyor at `pos` feeds into the side of the conveyor at `target` while
+// another conveyor feeds it from behind. A side input on its own is how lines turn
+// corners, so it's only flagged once it runs into the line's own packages.
+fn feeds_side_of_line(
+  tile_storage: &TileStorage,
+  directions: &impl Fn(Entity) -> Option<ConveyorDirection>,
+  pos: &TilePos,
+  target: &TilePos,
+) -> bool {
+  let (Some(direction), Some(target_direction)) =
+    (conveyor_direction_at(tile_storage, directions, pos), conveyor_direction_at(tile_storage, directions, target))
+  else {
+    return false;
+  };
+  if target_direction == direction || target_direction == direction.opposite() {
+    return false;
+  }
+  let Ok(behind) = (target.as_ivec2() - target_direction.offset()).to_tile_pos(&tile_storage.size) else { return false; };
+  conveyor_direction_at(tile_storage, directions, &behind) == Some(target_direction)
+}
+
+pub fn validate_layout(
+  tile_storage: &TileStorage,
+  directions: &impl Fn(Entity) -> Option<ConveyorDirection>,
+) -> LayoutReport {
+  let conveyors: Vec<_> = tile_positions(&tile_storage.size)
+    .filter(|pos| conveyor_direction_at(tile_storage, directions, pos).is_some())
+    .collect();
+
+  let mut parents: HashMap<_, _> = conveyors.iter().map(|pos| (*pos, *pos)).collect();
+  let mut problems = Vec::new();
+
+  for pos in conveyors.iter() {
+    match conveyor_output(tile_storage, directions, pos) {
+      Some(ConveyorOutput::Connected(target)) => {
+        let (root, target_root) = (find_root(&mut parents, *pos), find_root(&mut parents, target));
+        parents.insert(root, target_root);
+        if feeds_side_of_line(tile_storage, directions, pos, &target) {
+          problems.push(LayoutProblem {
+            kind: LayoutProblemKind::IntoOutput,
+            tiles: vec![*pos, target],
+          });
+        }
+      }
+      Some(ConveyorOutput::OffPlayfield) => problems.push(LayoutProblem {
+        kind: LayoutProblemKind::OffPlayfield,
+        tiles: vec![*pos],
+      }),
+      Some(ConveyorOutput::HeadOn(target)) => {
+        // two conveyors facing each other are a single problem, reported once
+        if (pos.y, pos.x) < (target.y, target.x) {
+          problems.push(LayoutProblem {
+            kind: LayoutProblemKind::IntoOutput,
+            tiles: vec![*pos, target],
+          });
+        }
+      }
+      Some(ConveyorOutput::Empty(_)) | None => {}
+    }
+  }
+
+  problems.extend(
+    find_cycles(tile_storage, directions)
+      .into_iter()
+      .map(|tiles| LayoutProblem { kind: LayoutProblemKind::Loop, tiles }),
+  );
+
+  let mut component_indices = HashMap::new();
+  let mut components: Vec<Vec<TilePos>> = Vec::new();
+  for pos in conveyors.iter() {
+    let root = find_root(&mut parents, *pos);
+    let index = *component_indices.entry(root).or_insert_with(|| {
+      components.push(Vec::new());
+      components.len() - 1
+    });
+    components[index].push(*pos);
+  }
+
+  LayoutReport { components, problems }
+}
+
+#[derive(Debug, Resource, Default)]
+pub struct LayoutValidation {
+  pub report: LayoutReport,
+}
+
+pub fn update_layout_validation(
+  mut updated_tiles: EventReader<UpdatedTile>,
+  mut validation: ResMut<LayoutValidation>,
+  tilemap: Query<(&TileStorage, &ConveyorTileLayer)>,
+  conveyor_directions: Query<&ConveyorDirection>,
+) {
+  if updated_tiles.is_empty() {
+    return;
+  }
+  updated_tiles.clear();
+
+  let Ok((tile_storage, _)) = tilemap.get_single() else {
+    error!(
+      "Tilemap query for the conveyor layer returned {} items when it only should have returned 1.",
+      tilemap.iter().len(),
+    );
+    return;
+  };
+
+  let directions = |entity: Entity| conveyor_directions.get(entity).ok().copied();
+  validation.report = validate_layout(tile_storage, &directions);
+}
+
+#[cfg(test)]
+mod validation_test {
+  use super::*;
+
+  struct TestLayout {
+    storage: TileStorage,
+    directions: HashMap<Entity, ConveyorDirection>,
+  }
+
+  impl TestLayout {
+    fn new(x: u32, y: u32) -> TestLayout {
+      TestLayout { storage: TileStorage::empty(TilemapSize { x, y }), directions: HashMap::new() }
+    }
+
+    fn with(mut self, x: u32, y: u32, direction: ConveyorDirection) -> TestLayout {
+      let entity = Entity::from_raw(self.directions.len() as u32);
+      self.storage.set(&TilePos { x, y }, entity);
+      self.directions.insert(entity, direction);
+      self
+    }
+
+    fn validate(&self) -> LayoutReport {
+      validate_layout(&self.storage, &|entity| self.directions.get(&entity).copied())
+    }
+  }
+
+  #[test]
+  fn straight_line_is_valid() {
+    let report = TestLayout::new(4, 4)
+      .with(0, 0, ConveyorDirection::East)
+      .with(1, 0, ConveyorDirection::East)
+      .with(2, 0, ConveyorDirection::North)
+      .validate();
+
+    assert!(report.is_valid());
+    assert_eq!(report.components.len(), 1);
+    assert_eq!(report.components[0].len(), 3);
+  }
+
+  #[test]
+  fn separate_components() {
+    let report = TestLayout::new(4, 4)
+      .with(0, 0, ConveyorDirection::North)
+      .with(0, 1, ConveyorDirection::North)
+      .with(3, 0, ConveyorDirection::North)
+      .with(3, 1, ConveyorDirection::West)
+      .validate();
+
+    assert_eq!(
+      report.components,
+      vec![
+        vec![TilePos { x: 0, y: 0 }, TilePos { x: 0, y: 1 }],
+        vec![TilePos { x: 3, y: 0 }, TilePos { x: 3, y: 1 }],
+      ],
+    );
+  }
+
+  #[test]
+  fn off_playfield() {
+    let report = TestLayout::new(2, 2)
+      .with(1, 1, ConveyorDirection::East)
+      .validate();
+
+    assert_eq!(
+      report.problems,
+      vec![LayoutProblem { kind: LayoutProblemKind::OffPlayfield, tiles: vec![TilePos { x: 1, y: 1 }] }],
+    );
+  }
+
+  #[test]
+  fn into_output() {
+    let report = TestLayout::new(4, 4)
+      .with(1, 1, ConveyorDirection::East)
+      .with(2, 1, ConveyorDirection::West)
+      .validate();
+
+    assert_eq!(
+      report.problems,
+      vec![LayoutProblem {
+        kind: LayoutProblemKind::IntoOutput,
+        tiles: vec![TilePos { x: 1, y: 1 }, TilePos { x: 2, y: 1 }],
+      }],
+    );
+  }
+
+  #[test]
+  fn side_feed_into_line() {
+    let report = TestLayout::new(4, 4)
+      .with(1, 0, ConveyorDirection::North)
+      .with(1, 1, ConveyorDirection::North)
+      .with(1, 2, ConveyorDirection::North)
+      .with(0, 1, ConveyorDirection::East)
+      // a side input without a conveyor behind it is a corner
+      .with(2, 2, ConveyorDirection::North)
+      .with(3, 2, ConveyorDirection::West)
+      .validate();
+
+    assert_eq!(
+      report.problems,
+      vec![LayoutProblem {
+        kind: LayoutProblemKind::IntoOutput,
+        tiles: vec![TilePos { x: 0, y: 1 }, TilePos { x: 1, y: 1 }],
+      }],
+    );
+  }
+
+  #[test]
+  fn closed_loop() {
+    let report = TestLayout::new(4, 4)
+      .with(1, 1, ConveyorDirection::East)
+      .with(2, 1, ConveyorDirection::North)
+      .with(2, 2, ConveyorDirection::West)
+      .with(1, 2, ConveyorDirection::South)
+      .with(0, 1, ConveyorDirection::East)
+      .validate();
+
+    // the loop closes into the side of the line coming in from (0, 1)
+    assert_eq!(report.problems.len(), 2);
+    assert_eq!(
+      report.problems[0],
+      LayoutProblem { kind: LayoutProblemKind::IntoOutput, tiles: vec![TilePos { x: 1, y: 2 }, TilePos { x: 1, y: 1 }] },
+    );
+    assert_eq!(report.problems[1].kind, LayoutProblemKind::Loop);
+    assert_eq!(report.problems[1].tiles.len(), 4);
+    assert!(!report.problems[1].tiles.contains(&TilePos { x: 0, y: 1 }));
+    assert_eq!(report.components.len(), 1);
+  }
+}
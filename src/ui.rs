@@ -1,19 +1,84 @@
+pub mod atlas_tile;
+pub mod build_menu;
+pub mod debug_overlay;
+pub mod orders_window;
+pub mod package_icon;
+pub mod problems_window;
+pub mod settings_menu;
+pub mod simulation_controls;
+pub mod stats_window;
+pub mod status_bar;
+pub mod tile_inspector;
 pub mod tile_preview;
+pub mod toasts;
 
 use bevy::prelude::*;
 
+pub use atlas_tile::plugin_exports::*;
+pub use build_menu::plugin_exports::*;
+pub use debug_overlay::plugin_exports::*;
+pub use orders_window::plugin_exports::*;
+pub use problems_window::plugin_exports::*;
+pub use settings_menu::plugin_exports::*;
+pub use stats_window::plugin_exports::*;
+pub use status_bar::plugin_exports::*;
+pub use tile_inspector::plugin_exports::*;
 pub use tile_preview::plugin_exports::*;
+pub use toasts::plugin_exports::*;
 
-use crate::GameSystemSet;
+use crate::{GameSystemSet, tile::overlay::highlight_tiles};
 
 pub mod prelude {
   pub use super::UiPlugin;
 }
 
+// Panels take space away from the screen, so they're laid out before the windows and
+// areas which anchor to what's left.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum UiSystemSet {
+  Panels,
+  Windows,
+}
+
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
   fn build(&self, app: &mut bevy::prelude::App) {
-    app.add_system(conveyor_window.in_set(GameSystemSet::PostTilePlacing));
+    app
+      .configure_set(UiSystemSet::Panels.in_set(GameSystemSet::PostTilePlacing))
+      .configure_set(UiSystemSet::Windows.in_set(GameSystemSet::PostTilePlacing).after(UiSystemSet::Panels))
+      .init_resource::<UiState>()
+      .add_startup_system(setup_conveyor_ui)
+      .add_system(register_conveyor_atlas_texture.in_set(GameSystemSet::PreInputCollection))
+      // status bar
+      .add_system(status_bar.in_set(UiSystemSet::Panels))
+      // building
+      .init_resource::<BuildMenuState>()
+      .add_system(build_menu.in_set(UiSystemSet::Windows))
+      .add_system(conveyor_window.in_set(UiSystemSet::Windows))
+      .add_system(orders_window.in_set(UiSystemSet::Windows))
+      // notifications
+      .init_resource::<Toasts>()
+      .add_systems((toast_rejected_placements, toast_order_events, show_toasts).chain().in_set(UiSystemSet::Windows))
+      // settings
+      .init_resource::<SettingsMenuState>()
+      .add_system(toggle_settings_menu.in_set(GameSystemSet::InputCollection))
+      .add_system(settings_menu.in_set(UiSystemSet::Windows).run_if(settings_menu_open))
+      // statistics
+      .init_resource::<StatsWindowState>()
+      .add_system(toggle_stats_window.in_set(GameSystemSet::InputCollection))
+      .add_system(stats_window.in_set(UiSystemSet::Windows))
+      // layout problems
+      .init_resource::<ProblemsWindowState>()
+      .add_system(toggle_problems_window.in_set(GameSystemSet::InputCollection))
+      .add_system(problems_window.in_set(UiSystemSet::Windows).before(highlight_tiles))
+      // hover inspector
+      .init_resource::<TileInspectorState>()
+      .add_system(toggle_tile_inspector.in_set(GameSystemSet::InputCollection))
+      .add_system(tile_inspector.in_set(UiSystemSet::Windows).run_if(tile_inspector_open))
+      // debug overlay
+      .init_resource::<DebugOverlayState>()
+      .add_system(toggle_debug_overlay.in_set(GameSystemSet::InputCollection))
+      .add_system(debug_overlay.in_set(UiSystemSet::Windows).run_if(debug_overlay_open));
   }
 }
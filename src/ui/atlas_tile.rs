@@ -0,0 +1,206 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::{egui::{self, epaint::{Mesh, Vertex}, Color32, Pos2, Sense}, EguiContexts};
+
+pub mod plugin_exports {
+  pub use super::register_conveyor_atlas_texture;
+  pub use super::setup_conveyor_ui;
+  pub use super::UiState;
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct UiState {
+  pub conveyor_atlas: Option<Handle<TextureAtlas>>,
+  // The atlas image registered with egui, once it has loaded.
+  conveyor_texture: Option<egui::TextureId>,
+}
+
+pub fn setup_conveyor_ui(
+  asset_server: Res<AssetServer>,
+  mut ui_state: ResMut<UiState>,
+) {
+  ui_state.conveyor_atlas = Some(asset_server.load("conveyor.aseprite#atlas"));
+}
+
+// Hands the atlas image to egui once instead of every frame it gets drawn.
+pub fn register_conveyor_atlas_texture(
+  mut contexts: EguiContexts,
+  mut ui_state: ResMut<UiState>,
+  texture_atlases: Res<Assets<TextureAtlas>>,
+) {
+  if ui_state.conveyor_texture.is_some() {
+    return;
+  }
+  let Some(atlas) = ui_state.conveyor_atlas.as_ref().and_then(|handle| texture_atlases.get(handle)) else { return; };
+  ui_state.conveyor_texture = Some(contexts.add_image(atlas.texture.clone()));
+}
+
+// The conveyor atlas, for drawing its tiles in the UI.
+#[derive(SystemParam)]
+pub struct ConveyorAtlas<'w> {
+  ui_state: Res<'w, UiState>,
+  texture_atlases: Res<'w, Assets<TextureAtlas>>,
+}
+
+impl<'w> ConveyorAtlas<'w> {
+  // None until the atlas has loaded.
+  pub fn tile(&self, index: u32, size: impl Into<egui::Vec2>) -> Option<AtlasTile> {
+    let texture_id = self.ui_state.conveyor_texture?;
+    let atlas = self.texture_atlases.get(self.ui_state.conveyor_atlas.as_ref()?)?;
+    Some(AtlasTile::new(texture_id, atlas, index as usize, size))
+  }
+}
+
+// Draws a tile out of a texture atlas, rotated and flipped the same way as on a tilemap.
+// Tiles which sense clicks are drawn as buttons.
+pub struct AtlasTile {
+  texture_id: egui::TextureId,
+  uv: egui::Rect,
+  size: egui::Vec2,
+  quarter_turns: u32,
+  flip: TileFlip,
+  tint: Color32,
+  sense: Sense,
+  selected: bool,
+}
+
+impl AtlasTile {
+  pub fn new(texture_id: egui::TextureId, atlas: &TextureAtlas, index: usize, size: impl Into<egui::Vec2>) -> AtlasTile {
+    let uv = atlas
+      .textures
+      .get(index)
+      .map(|rect| {
+        egui::Rect::from_min_max(
+          Pos2::new(rect.min.x / atlas.size.x, rect.min.y / atlas.size.y),
+          Pos2::new(rect.max.x / atlas.size.x, rect.max.y / atlas.size.y),
+        )
+      })
+      .unwrap_or(egui::Rect::NOTHING);
+    AtlasTile {
+      texture_id,
+      uv,
+      size: size.into(),
+      quarter_turns: 0,
+      flip: TileFlip::default(),
+      tint: Color32::WHITE,
+      sense: Sense::hover(),
+      selected: false,
+    }
+  }
+
+  // Turns the tile clockwise.
+  pub fn rotate(mut self, quarter_turns: u32) -> AtlasTile {
+    self.quarter_turns = quarter_turns % 4;
+    self
+  }
+
+  pub fn flip(mut self, flip: TileFlip) -> AtlasTile {
+    self.flip = flip;
+    self
+  }
+
+  // Multiplies the tile's colours, the same way a `TileColor` does on a tilemap.
+  pub fn tint(mut self, color: Color) -> AtlasTile {
+    let [red, green, blue, alpha] = color.as_rgba_f32().map(|channel| (channel * 255.0).round() as u8);
+    self.tint = Color32::from_rgba_unmultiplied(red, green, blue, alpha);
+    self
+  }
+
+  pub fn button(mut self) -> AtlasTile {
+    self.sense = Sense::click();
+    self
+  }
+
+  pub fn selected(mut self, selected: bool) -> AtlasTile {
+    self.selected = selected;
+    self
+  }
+
+  // The point of the tile image shown at `corner` of the widget, both in 0..1 from the top left.
+  fn image_point(&self, corner: egui::Vec2) -> egui::Vec2 {
+    let mut point = corner;
+    for _ in 0..self.quarter_turns {
+      point = egui::Vec2::new(point.y, 1.0 - point.x);
+    }
+    if self.flip.x {
+      point.x = 1.0 - point.x;
+    }
+    if self.flip.y {
+      point.y = 1.0 - point.y;
+    }
+    if self.flip.d {
+      point = egui::Vec2::new(point.y, point.x);
+    }
+    point
+  }
+
+  fn paint_at(&self, ui: &egui::Ui, rect: egui::Rect) {
+    let mut mesh = Mesh::with_texture(self.texture_id);
+    for corner in [egui::Vec2::new(0.0, 0.0), egui::Vec2::new(1.0, 0.0), egui::Vec2::new(1.0, 1.0), egui::Vec2::new(0.0, 1.0)] {
+      let image_point = self.image_point(corner);
+      mesh.vertices.push(Vertex {
+        pos: rect.min + corner * rect.size(),
+        uv: self.uv.min + image_point * self.uv.size(),
+        color: self.tint,
+      });
+    }
+    mesh.add_triangle(0, 1, 2);
+    mesh.add_triangle(0, 2, 3);
+    ui.painter().add(mesh);
+  }
+}
+
+impl egui::Widget for AtlasTile {
+  fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+    let is_button = self.sense.click;
+    let padding = match is_button {
+      true => egui::Vec2::splat(ui.spacing().button_padding.x),
+      false => egui::Vec2::ZERO,
+    };
+    let (rect, response) = ui.allocate_exact_size(self.size + 2.0 * padding, self.sense);
+    if !ui.is_rect_visible(rect) {
+      return response;
+    }
+
+    if is_button {
+      let (expansion, rounding, fill) = match self.selected {
+        true => (0.0, egui::Rounding::none(), ui.visuals().selection.bg_fill),
+        false => {
+          let visuals = ui.style().interact(&response);
+          (visuals.expansion, visuals.rounding, visuals.weak_bg_fill)
+        }
+      };
+      ui.painter().rect_filled(rect.expand(expansion), rounding, fill);
+    }
+    self.paint_at(ui, rect.shrink2(padding));
+    response
+  }
+}
+
+#[cfg(test)]
+mod atlas_tile_test {
+  use super::*;
+
+  fn tile(quarter_turns: u32, flip: TileFlip) -> AtlasTile {
+    let atlas = TextureAtlas::from_grid(Handle::default(), Vec2::new(16.0, 16.0), 4, 1, None, None);
+    AtlasTile::new(egui::TextureId::default(), &atlas, 1, [16.0, 16.0]).rotate(quarter_turns).flip(flip)
+  }
+
+  #[test]
+  fn uv_of_index() {
+    let tile = tile(0, TileFlip::default());
+    assert_eq!(tile.uv, egui::Rect::from_min_max(Pos2::new(0.25, 0.0), Pos2::new(0.5, 1.0)));
+  }
+
+  #[test]
+  fn rotation_matches_flips() {
+    // a clockwise quarter turn is the same as flipping along the diagonal, then horizontally
+    let rotated = tile(1, TileFlip::default());
+    let flipped = tile(0, TileFlip { x: true, y: false, d: true });
+    for corner in [egui::Vec2::new(0.0, 0.0), egui::Vec2::new(1.0, 0.0), egui::Vec2::new(1.0, 1.0), egui::Vec2::new(0.0, 1.0)] {
+      assert_eq!(rotated.image_point(corner), flipped.image_point(corner));
+    }
+    // the top left corner of a clockwise turned tile shows the bottom left of the image
+    assert_eq!(rotated.image_point(egui::Vec2::ZERO), egui::Vec2::new(0.0, 1.0));
+  }
+}
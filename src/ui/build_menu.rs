@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use bevy_egui::{egui::{self, Align2}, EguiContexts};
+
+use crate::{input::prelude::*, localisation::prelude::*, tile::prelude::*};
+
+use super::atlas_tile::ConveyorAtlas;
+
+pub mod plugin_exports {
+  pub use super::build_menu;
+  pub use super::BuildMenuState;
+}
+
+#[derive(Debug, Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct BuildMenuState {
+  pub category: TileCategory,
+}
+
+pub fn build_menu(
+  mut contexts: EguiContexts,
+  conveyor_atlas: ConveyorAtlas,
+  mut state: ResMut<BuildMenuState>,
+  selected_tile_type: Option<ResMut<SelectedTileType>>,
+  localisation: Res<Localisation>,
+) {
+  let Some(mut selected_tile_type) = selected_tile_type else { return; };
+  // picking a tile with the number keys or from the map opens its category
+  if selected_tile_type.is_changed() {
+    state.category = selected_tile_type.tile_type.category();
+  }
+
+  let ctx = contexts.ctx_mut();
+
+  egui::Area::new("build_menu")
+    .anchor(Align2::CENTER_BOTTOM, egui::Vec2::ZERO)
+    .show(ctx, |ui| {
+      egui::Frame::side_top_panel(&egui::Style::default()).show(ui, |ui| {
+        ui.horizontal(|ui| {
+          for category in TileCategory::VALUES {
+            if ui.selectable_label(state.category == category, localisation.name(&category)).clicked() {
+              state.category = category;
+            }
+          }
+        });
+        ui.separator();
+
+        ui.horizontal(|ui| {
+          let tile_types = TileType::VALUES.into_iter().enumerate().filter(|(_, tile_type)| tile_type.category() == state.category);
+          for (slot, tile_type) in tile_types {
+            let Some(icon) = conveyor_atlas.tile(tile_type.icon_index(), [32.0, 32.0]) else { return; };
+            let button = icon.tint(tile_type.tint()).button().selected(selected_tile_type.tile_type == tile_type);
+            let response = ui.add(button).on_hover_text(format!("{} ({})", localisation.name(&tile_type), slot + 1));
+            if response.clicked() {
+              selected_tile_type.tile_type = tile_type;
+            }
+          }
+        });
+      })
+    });
+}
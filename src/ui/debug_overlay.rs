@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::{egui::{self, Align2, Color32, Pos2, Stroke}, EguiContexts};
+
+use crate::{
+  camera::prelude::CursorPos,
+  input::chained_tile::{cursor_tile_position, ChainedTileResource},
+  tile::prelude::*,
+};
+
+pub mod plugin_exports {
+  pub use super::debug_overlay;
+  pub use super::debug_overlay_open;
+  pub use super::toggle_debug_overlay;
+  pub use super::DebugOverlayState;
+}
+
+const GRID_COLOR: Color32 = Color32::from_rgba_premultiplied(60, 60, 60, 60);
+const CHUNK_COLOR: Color32 = Color32::from_rgb(200, 60, 200);
+const ARROW_COLOR: Color32 = Color32::from_rgb(60, 200, 255);
+const CURSOR_COLOR: Color32 = Color32::WHITE;
+const LAST_TILE_COLOR: Color32 = Color32::YELLOW;
+// bevy_ecs_tilemap's chunk size when `TilemapRenderSettings` isn't inserted.
+const DEFAULT_RENDER_CHUNK_SIZE: UVec2 = UVec2::new(64, 64);
+
+#[derive(Debug, Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct DebugOverlayState {
+  pub open: bool,
+}
+
+pub fn toggle_debug_overlay(
+  keyboard_input: Res<Input<KeyCode>>,
+  mut state: ResMut<DebugOverlayState>,
+) {
+  if keyboard_input.just_pressed(KeyCode::F6) {
+    state.open = !state.open;
+  }
+}
+
+pub fn debug_overlay_open(state: Res<DebugOverlayState>) -> bool {
+  state.open
+}
+
+// Converts map positions, measured in tiles from the center of tile (0, 0), to egui points.
+struct MapToScreen<'a> {
+  camera: &'a Camera,
+  camera_transform: &'a GlobalTransform,
+  tilemap_transform: &'a Transform,
+  grid_size: Vec2,
+  viewport_height: f32,
+}
+
+impl<'a> MapToScreen<'a> {
+  fn convert(&self, map_pos: Vec2) -> Option<Pos2> {
+    let world = self.tilemap_transform.transform_point((map_pos * self.grid_size).extend(0.0));
+    let viewport = self.camera.world_to_viewport(self.camera_transform, world)?;
+    // viewport coordinates start at the bottom left, egui's at the top left
+    Some(Pos2::new(viewport.x, self.viewport_height - viewport.y))
+  }
+
+  fn line(&self, painter: &egui::Painter, start: Vec2, end: Vec2, color: Color32) {
+    if let (Some(start), Some(end)) = (self.convert(start), self.convert(end)) {
+      painter.line_segment([start, end], Stroke::new(1.0, color));
+    }
+  }
+
+  fn tile_outline(&self, painter: &egui::Painter, tile: IVec2, color: Color32) {
+    let center = tile.as_vec2();
+    if let (Some(min), Some(max)) = (self.convert(center - 0.5), self.convert(center + 0.5)) {
+      painter.rect_stroke(egui::Rect::from_two_pos(min, max), 0.0, Stroke::new(2.0, color));
+    }
+  }
+}
+
+// Draws the tile grid, render chunk boundaries and the direction of every conveyor over
+// the playfield, with the cursor and chained tile input state in the bottom left.
+pub fn debug_overlay(
+  mut contexts: EguiContexts,
+  cursor_pos: Res<CursorPos>,
+  chained_tile: Res<ChainedTileResource>,
+  render_settings: Option<Res<TilemapRenderSettings>>,
+  camera: Query<(&Camera, &GlobalTransform)>,
+  tilemap: Query<(&TilemapSize, &TilemapGridSize, &Transform), With<ConveyorTileLayer>>,
+  tiles: Query<(&TilePos, &ConveyorDirection)>,
+) {
+  let Ok((camera, camera_transform)) = camera.get_single() else { return; };
+  let Ok((tilemap_size, tilemap_grid_size, tilemap_transform)) = tilemap.get_single() else { return; };
+  let Some(viewport_size) = camera.logical_viewport_size() else { return; };
+
+  let map_to_screen = MapToScreen {
+    camera,
+    camera_transform,
+    tilemap_transform,
+    grid_size: Vec2::new(tilemap_grid_size.x, tilemap_grid_size.y),
+    viewport_height: viewport_size.y,
+  };
+  let cursor_tile = cursor_tile_position(&cursor_pos, tilemap_grid_size, tilemap_transform);
+
+  let ctx = contexts.ctx_mut();
+  let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("debug_overlay")));
+
+  let size = Vec2::new(tilemap_size.x as f32, tilemap_size.y as f32);
+  let chunk_size = render_settings
+    .map_or(DEFAULT_RENDER_CHUNK_SIZE, |settings| settings.render_chunk_size)
+    .max(UVec2::ONE);
+  for x in 0..=tilemap_size.x {
+    let color = match x % chunk_size.x == 0 {
+      true => CHUNK_COLOR,
+      false => GRID_COLOR,
+    };
+    map_to_screen.line(&painter, Vec2::new(x as f32 - 0.5, -0.5), Vec2::new(x as f32 - 0.5, size.y - 0.5), color);
+  }
+  for y in 0..=tilemap_size.y {
+    let color = match y % chunk_size.y == 0 {
+      true => CHUNK_COLOR,
+      false => GRID_COLOR,
+    };
+    map_to_screen.line(&painter, Vec2::new(-0.5, y as f32 - 0.5), Vec2::new(size.x - 0.5, y as f32 - 0.5), color);
+  }
+
+  for (tile_pos, direction) in tiles.iter() {
+    let center = Vec2::new(tile_pos.x as f32, tile_pos.y as f32);
+    let tip = center + direction.offset().as_vec2() * 0.35;
+    if let (Some(center), Some(tip)) = (map_to_screen.convert(center), map_to_screen.convert(tip)) {
+      painter.arrow(center, tip - center, Stroke::new(1.5, ARROW_COLOR));
+    }
+  }
+
+  map_to_screen.tile_outline(&painter, chained_tile.last_tile(), LAST_TILE_COLOR);
+  map_to_screen.tile_outline(&painter, cursor_tile, CURSOR_COLOR);
+
+  egui::Area::new("debug_overlay_info")
+    .anchor(Align2::LEFT_BOTTOM, egui::Vec2::ZERO)
+    .show(ctx, |ui| {
+      egui::Frame::side_top_panel(&egui::Style::default()).show(ui, |ui| {
+        let world = cursor_pos.world();
+        let screen = cursor_pos.screen();
+        let last_tile = chained_tile.last_tile();
+        ui.monospace(format!("Cursor world:  ({:.1}, {:.1})", world.x, world.y));
+        ui.monospace(format!("Cursor screen: ({:.1}, {:.1})", screen.x, screen.y));
+        ui.monospace(format!("Cursor tile:   ({}, {})", cursor_tile.x, cursor_tile.y));
+        ui.colored_label(LAST_TILE_COLOR, format!("Last chained tile: ({}, {})", last_tile.x, last_tile.y));
+      })
+    });
+}
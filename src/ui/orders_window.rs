@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use bevy_egui::{egui::{self, Color32}, EguiContexts};
+
+use crate::{delivery::prelude::*, localisation::prelude::*, simulation::prelude::*};
+
+use super::package_icon::PackageIcon;
+
+pub mod plugin_exports {
+  pub use super::orders_window;
+}
+
+pub fn orders_window(
+  mut contexts: EguiContexts,
+  orders: Option<Res<Orders>>,
+  simulation_time: Option<Res<SimulationTime>>,
+  customers: Query<&Customer>,
+  localisation: Res<Localisation>,
+) {
+  let (Some(orders), Some(simulation_time)) = (orders, simulation_time) else { return; };
+  // levels without customers have nothing to order
+  if customers.is_empty() {
+    return;
+  }
+
+  let ctx = contexts.ctx_mut();
+  let tick = simulation_time.tick();
+
+  egui::Window::new(localisation.text("orders.title"))
+    .id(egui::Id::new("orders_window"))
+    .anchor(egui::Align2::RIGHT_TOP, egui::Vec2::ZERO)
+    .resizable(false)
+    .show(ctx, |ui| {
+      if orders.active.is_empty() {
+        ui.label(localisation.text("orders.none"));
+        return;
+      }
+
+      egui::Grid::new("orders_grid").striped(true).show(ui, |ui| {
+        ui.strong(localisation.text("orders.customer"));
+        ui.strong(localisation.text("orders.package"));
+        ui.strong(localisation.text("orders.delivered"));
+        ui.strong(localisation.text("orders.time_left"));
+        ui.end_row();
+
+        for order in orders.active.iter() {
+          ui.label(format!("({}, {})", order.customer_pos.x, order.customer_pos.y));
+          ui.horizontal(|ui| {
+            ui.add(PackageIcon::new(order.kind));
+            ui.label(localisation.format("package.kind", &[("kind", &order.kind.0)]));
+          });
+          ui.label(format!("{} / {}", order.delivered, order.quantity));
+          let seconds = order.seconds_remaining(tick);
+          match seconds >= 0.0 {
+            true => ui.label(localisation.format("orders.seconds_left", &[("seconds", &seconds.ceil())])),
+            false => ui.colored_label(Color32::LIGHT_RED, localisation.format("orders.seconds_late", &[("seconds", &(-seconds).round())])),
+          };
+          ui.end_row();
+        }
+      });
+    });
+}
@@ -0,0 +1,40 @@
+use bevy_egui::egui::{self, epaint::CircleShape, Color32, Pos2, Sense, Shape, Stroke};
+
+use crate::stats::{PackageKind, PackageShape};
+
+const ICON_SIZE: f32 = 12.0;
+
+// A small outlined shape standing for a package kind.
+pub struct PackageIcon {
+  kind: PackageKind,
+}
+
+impl PackageIcon {
+  pub fn new(kind: PackageKind) -> PackageIcon {
+    PackageIcon { kind }
+  }
+}
+
+impl egui::Widget for PackageIcon {
+  fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(egui::Vec2::splat(ICON_SIZE), Sense::hover());
+    if ui.is_rect_visible(rect) {
+      let [red, green, blue] = self.kind.color();
+      let fill = Color32::from_rgb(red, green, blue);
+      let stroke = Stroke::new(1.0, Color32::BLACK);
+      let center = rect.center();
+      let radius = ICON_SIZE / 2.0 - 1.0;
+      let points = |offsets: &[(f32, f32)]| -> Vec<Pos2> {
+        offsets.iter().map(|(x, y)| center + egui::vec2(*x, *y) * radius).collect()
+      };
+      let shape = match self.kind.shape() {
+        PackageShape::Circle => Shape::Circle(CircleShape { center, radius, fill, stroke }),
+        PackageShape::Square => Shape::convex_polygon(points(&[(-0.8, -0.8), (0.8, -0.8), (0.8, 0.8), (-0.8, 0.8)]), fill, stroke),
+        PackageShape::Triangle => Shape::convex_polygon(points(&[(0.0, -1.0), (1.0, 0.8), (-1.0, 0.8)]), fill, stroke),
+        PackageShape::Diamond => Shape::convex_polygon(points(&[(0.0, -1.0), (1.0, 0.0), (0.0, 1.0), (-1.0, 0.0)]), fill, stroke),
+      };
+      ui.painter().add(shape);
+    }
+    response
+  }
+}
@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{localisation::prelude::*, tile::prelude::*};
+
+pub mod plugin_exports {
+  pub use super::problems_window;
+  pub use super::toggle_problems_window;
+  pub use super::ProblemsWindowState;
+}
+
+#[derive(Debug, Resource, Default)]
+pub struct ProblemsWindowState {
+  pub open: bool,
+  selected: Option<LayoutProblem>,
+}
+
+pub fn toggle_problems_window(
+  keyboard_input: Res<Input<KeyCode>>,
+  mut state: ResMut<ProblemsWindowState>,
+) {
+  if keyboard_input.just_pressed(KeyCode::F4) {
+    state.open = !state.open;
+  }
+}
+
+fn format_tiles(tiles: &[TilePos]) -> String {
+  tiles
+    .iter()
+    .map(|pos| format!("({}, {})", pos.x, pos.y))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+pub fn problems_window(
+  mut contexts: EguiContexts,
+  mut state: ResMut<ProblemsWindowState>,
+  validation: Res<LayoutValidation>,
+  mut highlighted: ResMut<HighlightedTiles>,
+  localisation: Res<Localisation>,
+) {
+  // forget the selection once the problem has been fixed
+  if state.selected.as_ref().is_some_and(|selected| !validation.report.problems.contains(selected)) {
+    state.selected = None;
+  }
+
+  if !state.open {
+    if !highlighted.tiles.is_empty() {
+      highlighted.tiles.clear();
+    }
+    return;
+  }
+
+  let ctx = contexts.ctx_mut();
+  let mut open = state.open;
+  let mut hovered = None;
+
+  egui::Window::new(localisation.text("problems.title"))
+    .id(egui::Id::new("problems_window"))
+    .open(&mut open)
+    .default_width(300.0)
+    .show(ctx, |ui| {
+      ui.label(localisation.format("problems.groups", &[("count", &validation.report.components.len())]));
+      ui.separator();
+
+      if validation.report.is_valid() {
+        ui.label(localisation.text("problems.none"));
+        return;
+      }
+
+      egui::ScrollArea::vertical().show(ui, |ui| {
+        for problem in validation.report.problems.iter() {
+          let is_selected = state.selected.as_ref() == Some(problem);
+          let response = ui
+            .selectable_label(is_selected, localisation.name(&problem.kind))
+            .on_hover_text(format_tiles(&problem.tiles));
+          if response.hovered() {
+            hovered = Some(problem.clone());
+          }
+          if response.clicked() {
+            state.selected = match is_selected {
+              true => None,
+              false => Some(problem.clone()),
+            };
+          }
+        }
+      });
+    });
+  state.open = open;
+
+  let tiles = hovered
+    .or_else(|| state.selected.clone())
+    .map(|problem| problem.tiles)
+    .unwrap_or_default();
+  if highlighted.tiles != tiles {
+    highlighted.tiles = tiles;
+  }
+}
@@ -0,0 +1,192 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_egui::{egui::{self, Align2}, EguiContexts};
+
+use crate::{
+  localisation::prelude::*,
+  settings::{prelude::*, PAN_SPEED_RANGE, UI_SCALE_RANGE, VOLUME_RANGE, ZOOM_RANGE},
+  tile::overlay::ConveyorOverlay,
+};
+
+use super::{
+  debug_overlay::DebugOverlayState, problems_window::ProblemsWindowState, stats_window::StatsWindowState,
+  tile_inspector::TileInspectorState,
+};
+
+pub mod plugin_exports {
+  pub use super::settings_menu;
+  pub use super::settings_menu_open;
+  pub use super::toggle_settings_menu;
+  pub use super::SettingsMenuState;
+}
+
+const RESOLUTIONS: [UVec2; 5] = [
+  UVec2::new(1280, 720),
+  UVec2::new(1366, 768),
+  UVec2::new(1600, 900),
+  UVec2::new(1920, 1080),
+  UVec2::new(2560, 1440),
+];
+
+#[derive(Debug, Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct SettingsMenuState {
+  pub open: bool,
+}
+
+pub fn toggle_settings_menu(
+  keyboard_input: Res<Input<KeyCode>>,
+  mut state: ResMut<SettingsMenuState>,
+) {
+  if keyboard_input.just_pressed(KeyCode::Escape) {
+    state.open = !state.open;
+  }
+}
+
+pub fn settings_menu_open(state: Res<SettingsMenuState>) -> bool {
+  state.open
+}
+
+// The windows and overlays which have their own hotkey.
+#[derive(SystemParam)]
+pub struct PanelToggles<'w> {
+  stats_window: ResMut<'w, StatsWindowState>,
+  problems_window: ResMut<'w, ProblemsWindowState>,
+  tile_inspector: ResMut<'w, TileInspectorState>,
+  debug_overlay: ResMut<'w, DebugOverlayState>,
+  conveyor_overlay: Option<ResMut<'w, ConveyorOverlay>>,
+}
+
+impl<'w> PanelToggles<'w> {
+  fn ui(&mut self, ui: &mut egui::Ui, localisation: &Localisation) {
+    let label = |key: &str, hotkey: &str| format!("{} ({})", localisation.text(key), hotkey);
+    ui.checkbox(&mut self.stats_window.open, label("panels.statistics", "F3"));
+    ui.checkbox(&mut self.problems_window.open, label("panels.problems", "F4"));
+    ui.checkbox(&mut self.tile_inspector.open, label("panels.tile_inspector", "I"));
+    if let Some(conveyor_overlay) = self.conveyor_overlay.as_mut() {
+      ui.checkbox(&mut conveyor_overlay.enabled, label("panels.conveyor_overlay", "F2"));
+    }
+    ui.checkbox(&mut self.debug_overlay.open, label("panels.debug_overlay", "F6"));
+  }
+}
+
+fn resolution_name(resolution: UVec2) -> String {
+  format!("{}x{}", resolution.x, resolution.y)
+}
+
+fn settings_ui(ui: &mut egui::Ui, settings: &mut Settings, localisation: &Localisation) {
+  ui.heading(localisation.text("settings.display"));
+  egui::Grid::new("display_settings").num_columns(2).show(ui, |ui| {
+    ui.label(localisation.text("settings.language"));
+    egui::ComboBox::from_id_source("language")
+      .selected_text(settings.language.name())
+      .show_ui(ui, |ui| {
+        for language in Language::VALUES {
+          ui.selectable_value(&mut settings.language, language, language.name());
+        }
+      });
+    ui.end_row();
+
+    ui.label(localisation.text("settings.window"));
+    egui::ComboBox::from_id_source("window_mode")
+      .selected_text(localisation.name(&settings.window_mode))
+      .show_ui(ui, |ui| {
+        for mode in WindowModeSetting::VALUES {
+          ui.selectable_value(&mut settings.window_mode, mode, localisation.name(&mode));
+        }
+      });
+    ui.end_row();
+
+    ui.label(localisation.text("settings.resolution"));
+    ui.add_enabled_ui(settings.window_mode == WindowModeSetting::Windowed, |ui| {
+      egui::ComboBox::from_id_source("resolution")
+        .selected_text(resolution_name(settings.resolution))
+        .show_ui(ui, |ui| {
+          for resolution in RESOLUTIONS {
+            ui.selectable_value(&mut settings.resolution, resolution, resolution_name(resolution));
+          }
+        });
+    });
+    ui.end_row();
+
+    ui.label(localisation.text("settings.ui_scale"));
+    ui.add(egui::Slider::new(&mut settings.ui_scale, UI_SCALE_RANGE).step_by(0.25));
+    ui.end_row();
+
+    ui.label(localisation.text("settings.colours"));
+    egui::ComboBox::from_id_source("palette")
+      .selected_text(localisation.name(&settings.palette))
+      .show_ui(ui, |ui| {
+        for palette in ColorPalette::VALUES {
+          ui.selectable_value(&mut settings.palette, palette, localisation.name(&palette));
+        }
+      });
+    ui.end_row();
+
+    ui.label(localisation.text("settings.conveyor_arrows"));
+    ui.checkbox(&mut settings.direction_arrows, localisation.text("settings.conveyor_arrows_description"));
+    ui.end_row();
+
+    ui.label(localisation.text("settings.effects"));
+    ui.checkbox(&mut settings.effects, localisation.text("settings.effects_description"));
+    ui.end_row();
+  });
+
+  ui.separator();
+  ui.heading(localisation.text("settings.camera"));
+  egui::Grid::new("camera_settings").num_columns(2).show(ui, |ui| {
+    ui.label(localisation.text("settings.zoom"));
+    ui.add(egui::Slider::new(&mut settings.zoom, ZOOM_RANGE)).on_hover_text(localisation.text("settings.zoom_hint"));
+    ui.end_row();
+
+    ui.label(localisation.text("settings.pan_speed"));
+    ui.add(egui::Slider::new(&mut settings.pan_speed, PAN_SPEED_RANGE).step_by(50.0));
+    ui.end_row();
+  });
+
+  ui.separator();
+  ui.heading(localisation.text("settings.audio"));
+  egui::Grid::new("audio_settings").num_columns(2).show(ui, |ui| {
+    for (key, volume) in [
+      ("settings.master_volume", &mut settings.master_volume),
+      ("settings.music_volume", &mut settings.music_volume),
+      ("settings.effects_volume", &mut settings.effects_volume),
+    ] {
+      ui.label(localisation.text(key));
+      ui.add(egui::Slider::new(volume, VOLUME_RANGE).custom_formatter(|volume, _| format!("{:.0}%", volume * 100.0)));
+      ui.end_row();
+    }
+  });
+}
+
+pub fn settings_menu(
+  mut contexts: EguiContexts,
+  mut state: ResMut<SettingsMenuState>,
+  mut settings: ResMut<Settings>,
+  mut panels: PanelToggles,
+  localisation: Res<Localisation>,
+) {
+  let ctx = contexts.ctx_mut();
+  let mut open = state.open;
+  let mut edited_settings = settings.clone();
+
+  egui::Window::new(localisation.text("settings.title"))
+    .id(egui::Id::new("settings_menu"))
+    .open(&mut open)
+    .anchor(Align2::CENTER_CENTER, egui::Vec2::ZERO)
+    .collapsible(false)
+    .resizable(false)
+    .show(ctx, |ui| {
+      settings_ui(ui, &mut edited_settings, &localisation);
+
+      ui.separator();
+      ui.heading(localisation.text("settings.panels"));
+      panels.ui(ui, &localisation);
+    });
+
+  state.open = open;
+  // the settings are edited as a copy so they're only marked as changed, and saved, when
+  // something actually changed
+  if edited_settings != *settings {
+    *settings = edited_settings;
+  }
+}
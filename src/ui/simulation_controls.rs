@@ -0,0 +1,24 @@
+use bevy_egui::egui;
+
+use crate::{localisation::prelude::*, simulation::prelude::*};
+
+// Pause, step and speed buttons, shown in the status bar.
+pub fn simulation_controls(ui: &mut egui::Ui, simulation_time: &mut SimulationTime, localisation: &Localisation) {
+  let pause_label = match simulation_time.paused {
+    true => "simulation.resume",
+    false => "simulation.pause",
+  };
+  if ui.button(localisation.text(pause_label)).on_hover_text(localisation.text("simulation.pause_key")).clicked() {
+    simulation_time.paused = !simulation_time.paused;
+  }
+  if ui.button(localisation.text("simulation.step")).on_hover_text(localisation.text("simulation.step_key")).clicked() {
+    simulation_time.request_step();
+  }
+
+  ui.separator();
+  for speed in SimulationSpeed::VALUES {
+    if ui.selectable_label(simulation_time.speed == speed, speed.name()).on_hover_text(localisation.text("simulation.speed_keys")).clicked() {
+      simulation_time.speed = speed;
+    }
+  }
+}
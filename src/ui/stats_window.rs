@@ -0,0 +1,99 @@
+use std::fs::File;
+
+use bevy::prelude::*;
+use bevy_egui::{egui::{self, plot::{Legend, Line, Plot, PlotPoints}}, EguiContexts};
+
+use crate::{localisation::prelude::*, stats::prelude::*};
+
+pub mod plugin_exports {
+  pub use super::stats_window;
+  pub use super::toggle_stats_window;
+  pub use super::StatsWindowState;
+}
+
+const CSV_EXPORT_PATH: &str = "stats.csv";
+
+#[derive(Debug, Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct StatsWindowState {
+  pub open: bool,
+}
+
+pub fn toggle_stats_window(
+  keyboard_input: Res<Input<KeyCode>>,
+  mut state: ResMut<StatsWindowState>,
+) {
+  if keyboard_input.just_pressed(KeyCode::F3) {
+    state.open = !state.open;
+  }
+}
+
+fn history_line(
+  stats: &ThroughputStats,
+  name: String,
+  value: impl Fn(&StatsWindow) -> f64,
+) -> Line {
+  let points: PlotPoints = stats
+    .history()
+    .map(|window| [window.start as f64, value(window)])
+    .collect();
+  Line::new(points).name(name)
+}
+
+pub fn stats_window(
+  mut contexts: EguiContexts,
+  mut state: ResMut<StatsWindowState>,
+  stats: Res<ThroughputStats>,
+  localisation: Res<Localisation>,
+) {
+  if !state.open {
+    return;
+  }
+
+  let ctx = contexts.ctx_mut();
+
+  egui::Window::new(localisation.text("statistics.title"))
+    .id(egui::Id::new("stats_window"))
+    .open(&mut state.open)
+    .default_width(400.0)
+    .show(ctx, |ui| {
+      let totals = stats.current().total_counts();
+      ui.label(localisation.format("statistics.current_window", &[
+        ("produced", &totals.produced),
+        ("delivered", &totals.delivered),
+        ("lost", &totals.lost),
+        ("utilisation", &(stats.current().average_utilisation() * 100.0).round()),
+      ]));
+
+      ui.separator();
+      ui.label(localisation.format("statistics.packages_per_window", &[("seconds", &stats.window_length)]));
+      Plot::new("package_stats_plot")
+        .height(160.0)
+        .include_y(0.0)
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+          for kind in stats.kinds() {
+            let name = |key: &str| localisation.format(key, &[("kind", &kind.0)]);
+            plot_ui.line(history_line(&stats, name("statistics.kind_delivered"), |window| window.counts(kind).delivered as f64));
+            plot_ui.line(history_line(&stats, name("statistics.kind_produced"), |window| window.counts(kind).produced as f64));
+            plot_ui.line(history_line(&stats, name("statistics.kind_lost"), |window| window.counts(kind).lost as f64));
+          }
+        });
+
+      ui.label(localisation.text("statistics.average_utilisation"));
+      Plot::new("conveyor_utilisation_plot")
+        .height(120.0)
+        .include_y(0.0)
+        .include_y(1.0)
+        .show(ui, |plot_ui| {
+          plot_ui.line(history_line(&stats, localisation.text("statistics.utilisation").to_string(), |window| window.average_utilisation() as f64));
+        });
+
+      if ui.button(localisation.text("statistics.export_csv")).clicked() {
+        match File::create(CSV_EXPORT_PATH).and_then(|file| stats.write_csv(file)) {
+          Ok(_) => info!("Wrote statistics to {}", CSV_EXPORT_PATH),
+          Err(error) => error!("Could not write statistics to {}: {}", CSV_EXPORT_PATH, error),
+        }
+      }
+    });
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{delivery::prelude::*, economy::prelude::*, localisation::prelude::*, simulation::prelude::*};
+
+use super::{settings_menu::SettingsMenuState, simulation_controls::simulation_controls};
+
+pub mod plugin_exports {
+  pub use super::status_bar;
+}
+
+fn format_time(seconds: u64) -> String {
+  format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+// Money, score and simulation time along the top of the screen.
+pub fn status_bar(
+  mut contexts: EguiContexts,
+  wallet: Option<Res<Wallet>>,
+  score: Option<Res<DeliveryScore>>,
+  simulation_time: Option<ResMut<SimulationTime>>,
+  mut settings_menu: ResMut<SettingsMenuState>,
+  localisation: Res<Localisation>,
+) {
+  let ctx = contexts.ctx_mut();
+
+  egui::TopBottomPanel::top("status_bar").show(ctx, |ui| {
+    ui.horizontal(|ui| {
+      if let Some(wallet) = &wallet {
+        ui.strong(format!("${}", wallet.balance()));
+        ui.separator();
+      }
+      if let Some(score) = &score {
+        ui.label(localisation.format("status.score", &[("score", &score.score)]));
+        ui.label(localisation.format("status.completed", &[("count", &score.completed)]));
+        ui.label(localisation.format("status.failed", &[("count", &score.failed)]));
+        ui.separator();
+      }
+      if let Some(mut simulation_time) = simulation_time {
+        ui.label(format_time(simulation_time.elapsed().as_secs()));
+        ui.separator();
+        simulation_controls(ui, &mut simulation_time, &localisation);
+      }
+
+      ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+        if ui.button(localisation.text("status.settings")).on_hover_text(localisation.text("status.settings_key")).clicked() {
+          settings_menu.open = !settings_menu.open;
+        }
+      });
+    });
+  });
+}
+
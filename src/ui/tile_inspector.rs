@@ -0,0 +1,143 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+  camera::prelude::CursorPos,
+  delivery::prelude::*,
+  input::chained_tile::cursor_tile_position,
+  localisation::prelude::*,
+  stats::prelude::*,
+  tile::{
+    network::{conveyor_output, ConveyorOutput},
+    prelude::*,
+    update_graphics::{conveyor_neighbors, ConveyorNeighbor},
+  },
+  vec2_traits::TilePosFromSigned,
+};
+
+use super::atlas_tile::ConveyorAtlas;
+
+pub mod plugin_exports {
+  pub use super::tile_inspector;
+  pub use super::tile_inspector_open;
+  pub use super::toggle_tile_inspector;
+  pub use super::TileInspectorState;
+}
+
+#[derive(Debug, Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct TileInspectorState {
+  pub open: bool,
+}
+
+pub fn toggle_tile_inspector(
+  keyboard_input: Res<Input<KeyCode>>,
+  mut state: ResMut<TileInspectorState>,
+) {
+  if keyboard_input.just_pressed(KeyCode::I) {
+    state.open = !state.open;
+  }
+}
+
+pub fn tile_inspector_open(state: Res<TileInspectorState>) -> bool {
+  state.open
+}
+
+fn describe_output(output: ConveyorOutput, localisation: &Localisation) -> String {
+  let (key, pos) = match output {
+    ConveyorOutput::Connected(pos) => ("inspector.output_connected", pos),
+    ConveyorOutput::OffPlayfield => return localisation.text("inspector.output_off_playfield").to_string(),
+    ConveyorOutput::Empty(pos) => ("inspector.output_empty", pos),
+    ConveyorOutput::HeadOn(pos) => ("inspector.output_head_on", pos),
+  };
+  localisation.format(key, &[("x", &pos.x), ("y", &pos.y)])
+}
+
+// The conveyor layer and what's placed on it.
+#[derive(SystemParam)]
+pub struct InspectedTiles<'w, 's> {
+  tilemap: Query<'w, 's, (&'static TileStorage, &'static TilemapGridSize, &'static Transform), With<ConveyorTileLayer>>,
+  tiles: Query<'w, 's, (&'static TileType, &'static ConveyorDirection, &'static TileTextureIndex, &'static TileFlip)>,
+  customers: Query<'w, 's, &'static TilePos, With<Customer>>,
+}
+
+// Shows what's known about the tile under the cursor in a tooltip.
+pub fn tile_inspector(
+  mut contexts: EguiContexts,
+  conveyor_atlas: ConveyorAtlas,
+  cursor_pos: Res<CursorPos>,
+  inspected: InspectedTiles,
+  stats: Option<Res<ThroughputStats>>,
+  localisation: Res<Localisation>,
+) {
+  let InspectedTiles { tilemap, tiles, customers } = inspected;
+  let Ok((tile_storage, tilemap_grid_size, tilemap_transform)) = tilemap.get_single() else { return; };
+  let position = cursor_tile_position(&cursor_pos, tilemap_grid_size, tilemap_transform);
+  let Ok(tile_pos) = position.to_tile_pos(&tile_storage.size) else { return; };
+
+  let ctx = contexts.ctx_mut();
+  if ctx.is_pointer_over_area() {
+    return;
+  }
+
+  let tile = tile_storage.get(&tile_pos).and_then(|entity| tiles.get(entity).ok());
+  let directions = |entity: Entity| tiles.get(entity).ok().map(|(_, direction, _, _)| *direction);
+
+  egui::show_tooltip_at_pointer(ctx, egui::Id::new("tile_inspector"), |ui| {
+    ui.strong(localisation.format("inspector.tile", &[("x", &tile_pos.x), ("y", &tile_pos.y)]));
+
+    let Some((tile_type, &direction, texture_index, &flip)) = tile else {
+      match customers.iter().any(|customer_pos| *customer_pos == tile_pos) {
+        true => ui.label(localisation.text("inspector.customer")),
+        false => ui.label(localisation.text("inspector.empty")),
+      };
+      return;
+    };
+
+    if let Some(icon) = conveyor_atlas.tile(texture_index.0, [32.0, 32.0]) {
+      ui.add(icon.flip(flip).tint(tile_type.tint()));
+    }
+
+    egui::Grid::new("tile_inspector_grid").show(ui, |ui| {
+      ui.label(localisation.text("inspector.kind"));
+      ui.label(localisation.name(tile_type));
+      ui.end_row();
+
+      ui.label(localisation.text("inspector.direction"));
+      ui.label(localisation.name(&direction));
+      ui.end_row();
+
+      let inputs: Vec<_> = direction
+        .neighbors_to_check_for_connections()
+        .into_iter()
+        .zip(conveyor_neighbors(tile_storage, &directions, &tile_pos, direction))
+        .filter(|(_, neighbor)| *neighbor == ConveyorNeighbor::Input)
+        .map(|(side, _)| localisation.name(&side))
+        .collect();
+      ui.label(localisation.text("inspector.inputs"));
+      match inputs.is_empty() {
+        true => ui.label(localisation.text("inspector.no_inputs")),
+        false => ui.label(inputs.join(", ")),
+      };
+      ui.end_row();
+
+      if let Some(output) = conveyor_output(tile_storage, &directions, &tile_pos) {
+        ui.label(localisation.text("inspector.outputs"));
+        ui.label(describe_output(output, &localisation));
+        ui.end_row();
+      }
+
+      // conveyors are sampled for packages on them, the share of occupied samples
+      // is the closest thing to a per-tile throughput the statistics keep
+      let load = stats
+        .as_ref()
+        .and_then(|stats| stats.history().next_back().and_then(|window| window.conveyors.get(&tile_pos).copied()));
+      if let Some(load) = load {
+        ui.label(localisation.text("inspector.load"));
+        ui.label(localisation.format("inspector.load_value", &[("percent", &(load.utilisation() * 100.0).round())]));
+        ui.end_row();
+      }
+    });
+  });
+}
@@ -1,7 +1,9 @@
 use bevy::{prelude::*, window::PrimaryWindow};
-use bevy_egui::{egui::{self, Align2, Pos2, Id}, EguiContexts};
+use bevy_egui::{egui::{self, Align2, Id}, EguiContexts};
 
-use crate::input::prelude::*;
+use crate::{economy::prelude::*, input::prelude::*, localisation::prelude::*};
+
+use super::atlas_tile::ConveyorAtlas;
 
 pub mod plugin_exports {
   pub use super::conveyor_window;
@@ -10,8 +12,11 @@ pub mod plugin_exports {
 pub fn conveyor_window(
   primary_window: Query<&PrimaryWindow>,
   tile_rotation: Option<Res<SelectedTileDirection>>,
+  selected_tile_type: Option<Res<SelectedTileType>>,
+  inventory: Option<Res<TileInventory>>,
+  localisation: Res<Localisation>,
   mut contexts: EguiContexts,
-  asset_server: Res<AssetServer>,
+  conveyor_atlas: ConveyorAtlas,
 ) {
   if primary_window.iter().count() != 1 {
     warn!("No primary window exists to display UI element on");
@@ -19,21 +24,19 @@ pub fn conveyor_window(
   }
 
   let Some(tile_rotation) = tile_rotation else { return; };
-  let image = contexts.add_image(asset_server.load("conveyor.png"));
+  let Some(preview) = conveyor_atlas.tile(tile_rotation.direction.texture_index(), [64.0, 64.0]) else { return; };
+  let tint = selected_tile_type.as_ref().map_or(Color::WHITE, |selected| selected.tile_type.tint());
 
   let ctx = contexts.ctx_mut();
 
-  let offset = 16.0 * tile_rotation.direction.texture_index() as f32;
-  let uv = egui::Rect::from_two_pos(
-    Pos2::new(offset / 464.0, 0.0),
-    Pos2::new((16.0 + offset) / 464.0, 1.0),
-  );
-
   egui::Area::new(Id::null())
     .anchor(Align2::RIGHT_BOTTOM, egui::Vec2::ZERO)
     .show(ctx, |ui| {
       egui::Frame::side_top_panel(&egui::Style::default()).show(ui, |ui| {
-        ui.add(egui::widgets::Image::new(image, [64.0, 64.0]).uv(uv));
+        ui.add(preview.tint(tint));
+        if let (Some(inventory), Some(selected_tile_type)) = (&inventory, &selected_tile_type) {
+          ui.label(localisation.format("inventory.left", &[("count", &inventory.remaining(selected_tile_type.tile_type))]));
+        }
       })
     });
 }
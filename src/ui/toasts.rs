@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use bevy_egui::{egui::{self, Align2, Color32}, EguiContexts};
+
+use crate::{
+  delivery::orders::{OrderEvent, OrderOutcome},
+  localisation::prelude::*,
+  tile::placement::{PlacementRejected, RejectionReason},
+};
+
+pub mod plugin_exports {
+  pub use super::show_toasts;
+  pub use super::toast_order_events;
+  pub use super::toast_rejected_placements;
+  pub use super::Toasts;
+}
+
+const TOAST_SECONDS: f64 = 3.0;
+const MAX_TOASTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+  Info,
+  Success,
+  Warning,
+}
+
+impl ToastKind {
+  fn color(&self) -> Color32 {
+    match self {
+      ToastKind::Info => Color32::LIGHT_GRAY,
+      ToastKind::Success => Color32::LIGHT_GREEN,
+      ToastKind::Warning => Color32::LIGHT_RED,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Toast {
+  kind: ToastKind,
+  text: String,
+  shown_until: f64,
+}
+
+// Short messages about things that happened in the game, which disappear on their own.
+#[derive(Debug, Resource, Default)]
+pub struct Toasts {
+  toasts: Vec<Toast>,
+}
+
+impl Toasts {
+  pub fn push(&mut self, time: &Time, kind: ToastKind, text: impl Into<String>) {
+    let text = text.into();
+    let shown_until = time.elapsed_seconds_f64() + TOAST_SECONDS;
+    // repeating the newest message keeps it up longer instead of stacking copies
+    if let Some(newest) = self.toasts.last_mut().filter(|toast| toast.kind == kind && toast.text == text) {
+      newest.shown_until = shown_until;
+      return;
+    }
+    self.toasts.push(Toast { kind, text, shown_until });
+    if self.toasts.len() > MAX_TOASTS {
+      self.toasts.remove(0);
+    }
+  }
+
+  fn remove_expired(&mut self, time: &Time) {
+    let now = time.elapsed_seconds_f64();
+    self.toasts.retain(|toast| toast.shown_until > now);
+  }
+}
+
+pub fn toast_rejected_placements(
+  time: Res<Time>,
+  mut rejected_placements: EventReader<PlacementRejected>,
+  mut toasts: ResMut<Toasts>,
+  localisation: Res<Localisation>,
+) {
+  // a dragged line sends one event per tile, only the last one is worth showing
+  let Some(rejected) = rejected_placements.iter().last() else { return; };
+  let text = match rejected.reason {
+    RejectionReason::CannotAfford { cost, balance } => localisation.format("toast.cannot_afford", &[("cost", &cost), ("balance", &balance)]),
+    RejectionReason::OutOfTiles(tile_type) => localisation.format("toast.out_of_tiles", &[("tile", &localisation.name(&tile_type))]),
+  };
+  toasts.push(&time, ToastKind::Warning, text);
+}
+
+pub fn toast_order_events(
+  time: Res<Time>,
+  mut order_events: EventReader<OrderEvent>,
+  mut toasts: ResMut<Toasts>,
+  localisation: Res<Localisation>,
+) {
+  for order_event in order_events.iter() {
+    let order = &order_event.order;
+    match order_event.outcome {
+      OrderOutcome::Issued => {}
+      OrderOutcome::Completed { reward, late } => {
+        let key = match late {
+          true => "toast.order_completed_late",
+          false => "toast.order_completed",
+        };
+        let text = localisation.format(key, &[("quantity", &order.quantity), ("kind", &order.kind.0), ("reward", &reward)]);
+        toasts.push(&time, ToastKind::Success, text);
+      }
+      OrderOutcome::Failed { penalty } => {
+        let text = localisation.format("toast.order_failed", &[("quantity", &order.quantity), ("kind", &order.kind.0), ("penalty", &penalty)]);
+        toasts.push(&time, ToastKind::Warning, text);
+      }
+    }
+  }
+}
+
+pub fn show_toasts(
+  mut contexts: EguiContexts,
+  time: Res<Time>,
+  mut toasts: ResMut<Toasts>,
+) {
+  toasts.remove_expired(&time);
+  if toasts.toasts.is_empty() {
+    return;
+  }
+
+  let ctx = contexts.ctx_mut();
+
+  egui::Area::new("toasts")
+    .anchor(Align2::LEFT_TOP, egui::Vec2::ZERO)
+    .interactable(false)
+    .show(ctx, |ui| {
+      for toast in toasts.toasts.iter() {
+        egui::Frame::popup(ui.style()).show(ui, |ui| {
+          ui.colored_label(toast.kind.color(), &toast.text);
+        });
+      }
+    });
+}
+
+#[cfg(test)]
+mod toasts_test {
+  use super::*;
+
+  #[test]
+  fn repeated_toasts_are_merged() {
+    let time = Time::default();
+    let mut toasts = Toasts::default();
+    toasts.push(&time, ToastKind::Warning, "No money");
+    toasts.push(&time, ToastKind::Warning, "No money");
+    assert_eq!(toasts.toasts.len(), 1);
+    toasts.push(&time, ToastKind::Info, "No money");
+    assert_eq!(toasts.toasts.len(), 2);
+  }
+
+  #[test]
+  fn oldest_toasts_make_room() {
+    let time = Time::default();
+    let mut toasts = Toasts::default();
+    for index in 0..MAX_TOASTS + 2 {
+      toasts.push(&time, ToastKind::Info, index.to_string());
+    }
+    assert_eq!(toasts.toasts.len(), MAX_TOASTS);
+    assert_eq!(toasts.toasts[0].text, "2");
+  }
+}